@@ -1,6 +1,7 @@
 use crate::cli::Cli;
 use crate::cli::Commands;
 use crate::cli::ConfigCommand;
+use crate::cli::ServersCommand;
 use crate::commands::*;
 
 #[derive(Debug, Clone)]
@@ -11,9 +12,14 @@ impl Application {
         match cli.command {
             Some(Commands::Config(ConfigCommand::Init(_))) => ConfigInitCommand.run(()),
             Some(Commands::Config(ConfigCommand::List(_))) => ConfigListCommand.run(()),
-            Some(Commands::Config(ConfigCommand::Set(set_args))) => ConfigSetCommand.run(set_args),
+            Some(Commands::Config(ConfigCommand::Set(set_args))) => ConfigSetCommand.run(*set_args),
             Some(Commands::Config(ConfigCommand::Reset(_))) => ConfigResetCommand.run(()),
             Some(Commands::Config(ConfigCommand::Delete(_))) => ConfigDeleteCommand.run(()),
+            Some(Commands::Apply(apply_args)) => ApplyCommand.run(apply_args),
+            Some(Commands::ExportConfig(export_args)) => ExportConfigCommand.run(export_args),
+            Some(Commands::Servers(ServersCommand::List(list_args))) => {
+                ServersListCommand.run(list_args)
+            }
             None => BenchmarkRunnerCommand.run(cli.args),
         }
     }
@@ -0,0 +1,470 @@
+use crate::args::ApplyTarget;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::path::PathBuf;
+
+const RESOLV_CONF_PATH: &str = "etc/resolv.conf";
+const SYSTEMD_RESOLVED_DROP_IN_PATH: &str = "etc/systemd/resolved.conf.d/dns-bench.conf";
+const NETWORK_MANAGER_CONNECTIONS_DIR: &str = "etc/NetworkManager/system-connections";
+const NETWORK_MANAGER_CONNECTION_EXTENSION: &str = "nmconnection";
+const BACKUP_SUFFIX: &str = ".dns-bench.bak";
+const GENERATED_HEADER: &str = "# Generated by dns-bench";
+/// The stub listeners of systemd-resolved.
+const SYSTEMD_RESOLVED_STUB_IPS: [IpAddr; 2] = [
+    IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53)),
+    IpAddr::V4(Ipv4Addr::new(127, 0, 0, 54)),
+];
+
+#[derive(Debug, derive_more::Error, derive_more::From)]
+pub enum ApplyError {
+    Io(io::Error),
+    #[from(ignore)]
+    NoServers,
+    #[from(ignore)]
+    MissingConnection,
+    #[from(ignore)]
+    InvalidConnection(#[error(not(source))] String),
+    #[from(ignore)]
+    ConnectionNotFound(#[error(not(source))] PathBuf),
+    #[from(ignore)]
+    ForwardingLoop(#[error(not(source))] IpAddr),
+    #[from(ignore)]
+    NoBackup(#[error(not(source))] PathBuf),
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::Io(e) => write!(f, "IO error: {e}"),
+            ApplyError::NoServers => write!(f, "No servers to apply"),
+            ApplyError::MissingConnection => write!(
+                f,
+                "The network-manager target requires the --connection option"
+            ),
+            ApplyError::InvalidConnection(connection) => {
+                write!(f, "Invalid connection name {connection:?}")
+            }
+            ApplyError::ConnectionNotFound(path) => {
+                write!(f, "Connection file {} does not exist", path.display())
+            }
+            ApplyError::NoBackup(path) => {
+                write!(f, "No backup found for {}", path.display())
+            }
+            ApplyError::ForwardingLoop(ip) => write!(
+                f,
+                "{ip} is the systemd-resolved stub itself, it cannot be its upstream server"
+            ),
+        }
+    }
+}
+
+/// Resolve the file written by the given target relative to `root`.
+pub fn target_path(
+    root: &Path,
+    target: ApplyTarget,
+    connection: Option<&str>,
+) -> Result<PathBuf, ApplyError> {
+    match target {
+        ApplyTarget::ResolvConf => Ok(root.join(RESOLV_CONF_PATH)),
+        ApplyTarget::SystemdResolved => Ok(root.join(SYSTEMD_RESOLVED_DROP_IN_PATH)),
+        ApplyTarget::NetworkManager => {
+            let connection = connection.ok_or(ApplyError::MissingConnection)?;
+            // The name must not leave the connections directory.
+            if connection.is_empty()
+                || connection.contains(['/', '\\'])
+                || connection.contains("..")
+            {
+                return Err(ApplyError::InvalidConnection(connection.to_string()));
+            }
+            Ok(root.join(NETWORK_MANAGER_CONNECTIONS_DIR).join(format!(
+                "{connection}.{NETWORK_MANAGER_CONNECTION_EXTENSION}"
+            )))
+        }
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(BACKUP_SUFFIX);
+    path.with_file_name(file_name)
+}
+
+/// Replace the `nameserver` lines of resolv.conf, keeping all other lines in place.
+fn render_resolv_conf(current: Option<&str>, servers: &[IpAddr]) -> String {
+    let mut lines = Vec::new();
+    let mut nameservers_written = false;
+
+    for line in current.unwrap_or_default().lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with(GENERATED_HEADER) {
+            continue;
+        }
+        if trimmed.starts_with("nameserver") {
+            if !nameservers_written {
+                lines.extend(servers.iter().map(|ip| format!("nameserver {ip}")));
+                nameservers_written = true;
+            }
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if !nameservers_written {
+        lines.extend(servers.iter().map(|ip| format!("nameserver {ip}")));
+    }
+
+    let mut content = format!("{GENERATED_HEADER}\n");
+    for line in lines {
+        content.push_str(&line);
+        content.push('\n');
+    }
+    content
+}
+
+fn render_systemd_resolved_drop_in(servers: &[IpAddr]) -> String {
+    let servers = servers
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!("{GENERATED_HEADER}\n[Resolve]\nDNS={servers}\n")
+}
+
+/// Set `dns` and `ignore-auto-dns` keys of the `[ipv4]` and `[ipv6]` sections of a
+/// NetworkManager keyfile, appending the sections when they do not exist.
+fn render_network_manager_connection(current: &str, servers: &[IpAddr]) -> String {
+    let sections = [
+        (
+            "ipv4",
+            servers.iter().filter(|ip| ip.is_ipv4()).collect::<Vec<_>>(),
+        ),
+        (
+            "ipv6",
+            servers.iter().filter(|ip| ip.is_ipv6()).collect::<Vec<_>>(),
+        ),
+    ];
+    let section_keys = |ips: &[&IpAddr]| {
+        let dns = ips.iter().map(|ip| format!("{ip};")).collect::<String>();
+        [format!("dns={dns}"), String::from("ignore-auto-dns=true")]
+    };
+
+    let mut lines = Vec::new();
+    let mut current_section: Option<&str> = None;
+    let mut seen_sections = Vec::new();
+    let flush = |section: Option<&str>, lines: &mut Vec<String>| {
+        if let Some((_, ips)) = sections.iter().find(|(name, _)| Some(*name) == section) {
+            if !ips.is_empty() {
+                // Keep a blank separator line after the section keys.
+                let blank = lines.last().is_some_and(|l: &String| l.trim().is_empty());
+                if blank {
+                    lines.pop();
+                }
+                lines.extend(section_keys(ips));
+                if blank {
+                    lines.push(String::new());
+                }
+            }
+        }
+    };
+
+    for line in current.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            flush(current_section, &mut lines);
+            let name = &trimmed[1..trimmed.len() - 1];
+            current_section = sections
+                .iter()
+                .find(|(section, _)| *section == name)
+                .map(|(section, _)| *section);
+            if let Some(section) = current_section {
+                seen_sections.push(section);
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        let is_replaced_key = current_section
+            .and_then(|section| sections.iter().find(|(name, _)| *name == section))
+            .is_some_and(|(_, ips)| {
+                !ips.is_empty()
+                    && (trimmed.starts_with("dns=") || trimmed.starts_with("ignore-auto-dns="))
+            });
+        if !is_replaced_key {
+            lines.push(line.to_string());
+        }
+    }
+    flush(current_section, &mut lines);
+
+    for (section, ips) in &sections {
+        if !ips.is_empty() && !seen_sections.contains(section) {
+            if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{section}]"));
+            lines.extend(section_keys(ips));
+        }
+    }
+
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+/// The change an apply run is going to make.
+#[derive(Debug, Clone)]
+pub struct ApplyPlan {
+    pub path: PathBuf,
+    pub current: Option<String>,
+    pub updated: String,
+}
+
+impl ApplyPlan {
+    pub fn new(path: PathBuf, target: ApplyTarget, servers: &[IpAddr]) -> Result<Self, ApplyError> {
+        if servers.is_empty() {
+            return Err(ApplyError::NoServers);
+        }
+        if target == ApplyTarget::SystemdResolved {
+            if let Some(ip) = servers
+                .iter()
+                .find(|ip| SYSTEMD_RESOLVED_STUB_IPS.contains(ip))
+            {
+                return Err(ApplyError::ForwardingLoop(*ip));
+            }
+        }
+
+        let current = match fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let updated = match target {
+            ApplyTarget::ResolvConf => render_resolv_conf(current.as_deref(), servers),
+            ApplyTarget::SystemdResolved => render_systemd_resolved_drop_in(servers),
+            ApplyTarget::NetworkManager => {
+                let Some(current) = &current else {
+                    return Err(ApplyError::ConnectionNotFound(path));
+                };
+                render_network_manager_connection(current, servers)
+            }
+        };
+
+        Ok(ApplyPlan {
+            path,
+            current,
+            updated,
+        })
+    }
+
+    /// Unified diff between the current and the updated file content.
+    pub fn diff(&self) -> String {
+        let path = self.path.display().to_string();
+        similar::TextDiff::from_lines(self.current.as_deref().unwrap_or_default(), &self.updated)
+            .unified_diff()
+            .header(&path, &path)
+            .to_string()
+    }
+
+    /// Write the updated content. The original file is backed up once, so repeated runs
+    /// always restore the state from before the first apply.
+    pub fn apply(&self) -> Result<Option<PathBuf>, ApplyError> {
+        let backup = backup_path(&self.path);
+        let original_exists = self.path.symlink_metadata().is_ok();
+
+        if original_exists && backup.symlink_metadata().is_err() {
+            copy_preserving_symlink(&self.path, &backup)?;
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Do not write through a symlink (e.g. resolv.conf managed by systemd-resolved).
+        if self.path.is_symlink() {
+            fs::remove_file(&self.path)?;
+        }
+        fs::write(&self.path, &self.updated)?;
+
+        Ok(original_exists.then_some(backup))
+    }
+}
+
+#[cfg(unix)]
+fn copy_preserving_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(from)?, to)
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_preserving_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+/// Put the backup created by [`ApplyPlan::apply`] back in place. Files generated from scratch
+/// (without an original to back up) are removed instead.
+pub fn restore(path: &Path) -> Result<(), ApplyError> {
+    let backup = backup_path(path);
+    if backup.symlink_metadata().is_ok() {
+        if path.symlink_metadata().is_ok() {
+            fs::remove_file(path)?;
+        }
+        fs::rename(&backup, path)?;
+        return Ok(());
+    }
+
+    let is_generated = fs::read_to_string(path)
+        .map(|content| content.starts_with(GENERATED_HEADER))
+        .unwrap_or(false);
+    if is_generated {
+        fs::remove_file(path)?;
+        Ok(())
+    } else {
+        Err(ApplyError::NoBackup(path.to_path_buf()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::net::Ipv6Addr;
+    use std::str::FromStr;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("dns-bench-apply-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn servers() -> Vec<IpAddr> {
+        vec![
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+        ]
+    }
+
+    #[test]
+    fn test_render_resolv_conf() {
+        let current = "# comment\nsearch example.com\nnameserver 192.168.0.1\noptions edns0\nnameserver 192.168.0.2\n";
+        let updated = render_resolv_conf(Some(current), &servers());
+        assert_eq!(
+            updated,
+            "# Generated by dns-bench\n# comment\nsearch example.com\nnameserver 1.1.1.1\nnameserver 8.8.8.8\noptions edns0\n"
+        );
+        // Applying twice does not duplicate the header.
+        assert_eq!(render_resolv_conf(Some(&updated), &servers()), updated);
+    }
+
+    #[test]
+    fn test_render_network_manager_connection() {
+        let current = "[connection]\nid=Home\n\n[ipv4]\nmethod=auto\ndns=192.168.0.1;\n\n[proxy]\n";
+        let mut with_v6 = servers();
+        with_v6.push(IpAddr::V6(
+            Ipv6Addr::from_str("2606:4700:4700::1111").unwrap(),
+        ));
+        assert_eq!(
+            render_network_manager_connection(current, &with_v6),
+            "[connection]\nid=Home\n\n[ipv4]\nmethod=auto\ndns=1.1.1.1;8.8.8.8;\nignore-auto-dns=true\n\n[proxy]\n\n[ipv6]\ndns=2606:4700:4700::1111;\nignore-auto-dns=true\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_and_restore_resolv_conf() {
+        let root = temp_root("resolv-conf");
+        let path = target_path(&root, ApplyTarget::ResolvConf, None).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "nameserver 192.168.0.1\n").unwrap();
+
+        let plan = ApplyPlan::new(path.clone(), ApplyTarget::ResolvConf, &servers()).unwrap();
+        assert_eq!(
+            plan.diff(),
+            format!(
+                "--- {0}\n+++ {0}\n@@ -1 +1,3 @@\n-nameserver 192.168.0.1\n+# Generated by dns-bench\n+nameserver 1.1.1.1\n+nameserver 8.8.8.8\n",
+                path.display()
+            )
+        );
+
+        let backup = plan.apply().unwrap().unwrap();
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "nameserver 192.168.0.1\n"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), plan.updated);
+
+        // A second apply keeps the original backup.
+        ApplyPlan::new(path.clone(), ApplyTarget::ResolvConf, &servers()[..1])
+            .unwrap()
+            .apply()
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "nameserver 192.168.0.1\n"
+        );
+
+        restore(&path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "nameserver 192.168.0.1\n"
+        );
+        assert!(!backup.exists());
+        assert!(matches!(restore(&path), Err(ApplyError::NoBackup(_))));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_apply_and_restore_systemd_resolved() {
+        let root = temp_root("systemd-resolved");
+        let path = target_path(&root, ApplyTarget::SystemdResolved, None).unwrap();
+
+        let plan = ApplyPlan::new(path.clone(), ApplyTarget::SystemdResolved, &servers()).unwrap();
+        assert_eq!(plan.apply().unwrap(), None);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Generated by dns-bench\n[Resolve]\nDNS=1.1.1.1 8.8.8.8\n"
+        );
+
+        restore(&path).unwrap();
+        assert!(!path.exists());
+
+        assert!(matches!(
+            ApplyPlan::new(
+                path,
+                ApplyTarget::SystemdResolved,
+                &[IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53))]
+            ),
+            Err(ApplyError::ForwardingLoop(_))
+        ));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_network_manager_requires_connection() {
+        let root = temp_root("network-manager");
+        assert!(matches!(
+            target_path(&root, ApplyTarget::NetworkManager, None),
+            Err(ApplyError::MissingConnection)
+        ));
+        let path = target_path(&root, ApplyTarget::NetworkManager, Some("Home")).unwrap();
+        assert!(path.ends_with("etc/NetworkManager/system-connections/Home.nmconnection"));
+        for connection in ["../../shadow", "a/b", "..", ""] {
+            assert!(matches!(
+                target_path(&root, ApplyTarget::NetworkManager, Some(connection)),
+                Err(ApplyError::InvalidConnection(_))
+            ));
+        }
+        assert!(matches!(
+            ApplyPlan::new(path, ApplyTarget::NetworkManager, &servers()),
+            Err(ApplyError::ConnectionNotFound(_))
+        ));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -49,13 +49,41 @@ impl From<IpAddr> for LookupIpStrategy {
     }
 }
 
+impl From<std::net::IpAddr> for IpAddr {
+    fn from(val: std::net::IpAddr) -> Self {
+        match val {
+            std::net::IpAddr::V4(_) => IpAddr::V4,
+            std::net::IpAddr::V6(_) => IpAddr::V6,
+        }
+    }
+}
+
 argument_impl_from_str!(IpAddr);
 argument_impl_display!(IpAddr);
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
 pub enum Protocol {
+    #[serde(alias = "tcp")]
     Tcp,
+    #[serde(alias = "udp")]
     Udp,
+    /// DNS over TLS.
+    #[serde(alias = "tls")]
+    Tls,
+    /// DNS over HTTPS.
+    #[serde(alias = "https")]
+    Https,
+}
+
+impl Protocol {
+    /// The port servers listen on for this protocol by default.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Protocol::Tcp | Protocol::Udp => 53,
+            Protocol::Tls => 853,
+            Protocol::Https => 443,
+        }
+    }
 }
 
 impl From<Protocol> for ResolverProtocol {
@@ -63,6 +91,8 @@ impl From<Protocol> for ResolverProtocol {
         match val {
             Protocol::Tcp => ResolverProtocol::Tcp,
             Protocol::Udp => ResolverProtocol::Udp,
+            Protocol::Tls => ResolverProtocol::Tls,
+            Protocol::Https => ResolverProtocol::Https,
         }
     }
 }
@@ -103,3 +133,78 @@ pub enum Format {
 
 argument_impl_from_str!(Format);
 argument_impl_display!(Format);
+
+#[derive(
+    Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+pub enum Profile {
+    /// Query every server once per request with the configured timeout.
+    #[default]
+    Default,
+    /// Query with the timeout, attempts, rotation and search domains of /etc/resolv.conf, like glibc.
+    SystemLike,
+}
+
+argument_impl_from_str!(Profile);
+argument_impl_display!(Profile);
+
+#[derive(
+    Debug, Default, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize,
+)]
+pub enum Schedule {
+    /// Make all requests to a server back-to-back, one server after another.
+    #[default]
+    Sequential,
+    /// Make one request to every server in turn until all requests are made.
+    RoundRobin,
+    /// Make the requests to all servers in random order.
+    Shuffle,
+}
+
+argument_impl_from_str!(Schedule);
+argument_impl_display!(Schedule);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum ApplyTarget {
+    /// Write nameserver lines into /etc/resolv.conf.
+    ResolvConf,
+    /// Generate a drop-in for systemd-resolved in /etc/systemd/resolved.conf.d.
+    SystemdResolved,
+    /// Set the DNS servers of a NetworkManager connection keyfile.
+    NetworkManager,
+}
+
+argument_impl_from_str!(ApplyTarget);
+argument_impl_display!(ApplyTarget);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum ForwarderTarget {
+    /// dnsmasq `server=` lines.
+    Dnsmasq,
+    /// Unbound `forward-zone` clause.
+    Unbound,
+    /// CoreDNS server block with a `forward` plugin.
+    Coredns,
+    /// Pi-hole setupVars.conf upstream list.
+    Pihole,
+}
+
+argument_impl_from_str!(ForwarderTarget);
+argument_impl_display!(ForwarderTarget);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
+pub enum ServersListFormat {
+    /// A table for reading.
+    Table,
+    /// The JSON custom servers format.
+    Json,
+    /// The TOML custom servers format.
+    Toml,
+    /// The YAML custom servers format.
+    Yaml,
+    /// The plain `name;ip:port` custom servers format, for servers without other settings.
+    Plain,
+}
+
+argument_impl_from_str!(ServersListFormat);
+argument_impl_display!(ServersListFormat);
@@ -1,36 +1,55 @@
 use crate::args::Format;
 use crate::args::IpAddr as ArgIpAddr;
+use crate::args::Profile;
+use crate::args::Schedule;
 use crate::cli;
 use crate::config;
 use crate::custom;
-use crate::gateway::get_gateway_addr;
+use crate::gateway::get_gateways;
+use crate::gateway::Gateway;
+use crate::gateway::GatewaySkipReason;
+use crate::gateway::SkippedGateway;
 use crate::output::get_output_formatter;
+use crate::output::OutputFormatter;
 use crate::output::OutputFormatterContext;
+use crate::output::SvgChartOutputFormatter;
+use crate::recommend::recommend;
+use crate::recommend::Recommendation;
+use crate::resolver::create_async_resolver;
 use crate::resolver::create_resolver;
+use crate::resolver::create_system_like_resolver;
+use crate::resolver::BindTarget;
+use crate::resolver::BootstrapResolver;
+use crate::resolver::BoundAsyncResolver;
 use crate::result::MeasureResult;
 use crate::result::RawResultEntry;
 use crate::result::TimeResult;
 use crate::servers;
 use crate::system::get_system_dns;
+use crate::system::read_resolv_conf;
+use crate::system::ResolvConf;
+use crate::system::SystemDnsServer;
 
 use indicatif::MultiProgress;
 use indicatif::ProgressBar;
 use indicatif::ProgressStyle;
+use rand::seq::SliceRandom;
 use std::collections;
+use std::fs;
 use std::io;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
-use std::net::SocketAddr;
 use std::process;
 use std::sync;
-use std::thread;
+use std::sync::atomic;
 use std::time::Duration;
 use std::time::Instant;
 
 const PROGRESS_BAR_TICK_INTERVAL_MILLIS: u64 = 50;
-const GATEWAY_RESPONSIVENESS_TEST_TIMEOUT_MILLIS: u64 = 200;
 const POISONED_MUTEX_ERR: &str = "Poisoned mutex error";
+/// Exit code of a process terminated by SIGINT.
+const INTERRUPTED_EXIT_CODE: i32 = 130;
 
 const REDUCE_TIMEOUT_AFTER_CONSECUTIVE_FAILURES: u32 = 8;
 const REDUCED_TIMEOUT_MS: u64 = 500;
@@ -43,59 +62,105 @@ pub struct BenchmarkRunner {
     arguments: cli::DefaultArgs,
     /// The configuration.
     config: config::DnsBenchConfig,
+    /// The DNS entries selected for the benchmark.
+    selected_dns_entries: Vec<servers::DnsEntry>,
     /// The DNS entries.
     dns_entries: sync::Arc<sync::Mutex<collections::VecDeque<servers::DnsEntry>>>,
     /// The result entries.
     result_entries: sync::Arc<sync::Mutex<Vec<RawResultEntry>>>,
-    /// The threads.
-    threads: Vec<thread::JoinHandle<()>>,
     /// The progress bar.
     multi_progress: Option<MultiProgress>,
     /// The benchmark start time.
     bench_start_time: Option<Instant>,
     /// The set of system DNS server IPs (for marking in table).
     system_dns_ips: Option<Vec<IpAddr>>,
+    /// The DNS servers the system is configured with, in the order the system uses them.
+    system_dns_servers: Vec<SystemDnsServer>,
+    /// The resolv.conf the system-like profile mirrors, `None` for the default profile.
+    resolv_conf: Option<ResolvConf>,
+    /// The default gateways, the one the OS uses first.
+    gateways: Vec<Gateway>,
+    /// The gateways whose DNS is not benchmarked, with the reason.
+    skipped_gateways: Vec<SkippedGateway>,
+    /// Set by Ctrl-C, stops the workers from starting new servers and requests.
+    interrupted: sync::Arc<atomic::AtomicBool>,
 }
 
 impl BenchmarkRunner {
     /// Create a new instance of the application.
     pub fn new(arguments: cli::DefaultArgs) -> Self {
         let mut config = config::DnsBenchConfig::try_load_from_file().unwrap_or_default();
-        config.resolve_args(&arguments.args);
+        if let Err(e) = config.resolve_args(&arguments.args) {
+            eprintln!("Invalid configuration: {e}");
+            process::exit(1);
+        }
 
         // Try to get system DNS servers here and store their IPs for later marking.
-        let system_dns_ips = if !config.skip_system_servers {
-            match get_system_dns() {
-                Ok((primary, secondary)) => {
-                    let mut ips = vec![primary];
-                    if let Some(sec) = secondary {
-                        if sec != primary {
-                            ips.push(sec);
-                        }
-                    }
-                    Some(ips)
+        let system_dns_servers = if !config.skip_system_servers {
+            get_system_dns().unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to retrieve system DNS servers: {e}.\n\
+                    Proceeding with built-in or custom list only..."
+                );
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        let system_dns_ips = (!system_dns_servers.is_empty()).then(|| {
+            let mut ips = Vec::new();
+            for server in &system_dns_servers {
+                if !ips.contains(&server.ip) {
+                    ips.push(server.ip);
                 }
+            }
+            ips
+        });
+
+        let mut skipped_gateways = Vec::new();
+        let gateways = if !config.skip_gateway_detection {
+            get_gateways(config.name_servers_ip).unwrap_or_else(|e| {
+                eprintln!("Failed to detect gateway IP address: {e}");
+                skipped_gateways.push(SkippedGateway {
+                    ip: None,
+                    interface: None,
+                    reason: GatewaySkipReason::NotDetected,
+                    message: Some(e.to_string()),
+                });
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+
+        let resolv_conf = match config.profile {
+            Profile::Default => None,
+            Profile::SystemLike => match read_resolv_conf() {
+                Ok(resolv_conf) => Some(resolv_conf),
                 Err(e) => {
                     eprintln!(
-                        "Failed to retrieve system DNS servers: {e}.\n\
-                        Proceeding with built-in or custom list only..."
+                        "Failed to read /etc/resolv.conf: {e}.\n\
+                        Proceeding with the default profile..."
                     );
                     None
                 }
-            }
-        } else {
-            None
+            },
         };
 
         Self {
             arguments,
             config,
+            selected_dns_entries: Vec::new(),
             dns_entries: sync::Arc::new(sync::Mutex::new(collections::VecDeque::default())),
             result_entries: sync::Arc::new(sync::Mutex::new(Vec::new())),
-            threads: Vec::new(),
             multi_progress: None,
             bench_start_time: None,
             system_dns_ips,
+            system_dns_servers,
+            resolv_conf,
+            gateways,
+            skipped_gateways,
+            interrupted: sync::Arc::new(atomic::AtomicBool::new(false)),
         }
     }
 
@@ -103,14 +168,75 @@ impl BenchmarkRunner {
     pub fn run(&mut self) {
         self.print_config_summary();
         self.save_config();
+        self.measure();
+        self.print_result();
+        self.write_chart();
+        self.print_bench_elapsed_time();
+        if self.is_interrupted() {
+            process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    }
+
+    /// Benchmark all servers without printing the results.
+    pub fn measure(&mut self) {
         self.fill_dns_entries();
         self.init_multi_progress();
+        self.install_interrupt_handler();
         self.bench_start_time();
-        self.spawn_threads();
-        self.await_threads();
+        self.run_workers();
         self.sort_result_entries();
-        self.print_result();
-        self.print_bench_elapsed_time();
+        self.apply_color_thresholds();
+    }
+
+    /// The recommended pair among the servers the system configuration can point at.
+    pub fn applicable_recommendation(&self) -> Option<Recommendation> {
+        let results = self.result_entries.lock().expect(POISONED_MUTEX_ERR);
+        self.recommendation(&results)
+    }
+
+    /// The recommended pair among the servers the system configuration can point at, see
+    /// [`servers::DnsEntry::is_applicable`], other than the system DNS servers and the gateways.
+    fn recommendation(&self, results: &[RawResultEntry]) -> Option<Recommendation> {
+        recommend(
+            results,
+            self.system_dns_ips.as_deref().unwrap_or_default(),
+            |result| {
+                !self.gateways.iter().any(|gateway| gateway.ip == result.ip)
+                    && self
+                        .dns_entry_of(result)
+                        .is_some_and(|entry| entry.is_applicable(self.config.protocol))
+            },
+        )
+    }
+
+    /// The DNS entries of the fastest responsive servers, in result order.
+    pub fn fastest_dns_entries(&self, count: usize) -> Vec<servers::DnsEntry> {
+        let results = self.result_entries.lock().expect(POISONED_MUTEX_ERR);
+        results
+            .iter()
+            .filter(|result| result.avg_duration.is_succeeded())
+            .filter_map(|result| self.dns_entry_of(result).cloned())
+            .take(count)
+            .collect()
+    }
+
+    /// The benchmarked DNS entry a result belongs to, the same server may be benchmarked once
+    /// per source of the queries.
+    fn dns_entry_of(&self, result: &RawResultEntry) -> Option<&servers::DnsEntry> {
+        self.selected_dns_entries.iter().find(|entry| {
+            entry.socket_addr.ip() == result.ip && entry.bind == result.bind.map(BindTarget::Addr)
+        })
+    }
+
+    /// Collect the servers the benchmark would run against, without benchmarking them.
+    pub fn dns_entries(&mut self) -> Vec<servers::DnsEntry> {
+        self.fill_dns_entries();
+        self.selected_dns_entries.clone()
+    }
+
+    /// The configuration the benchmark runs with.
+    pub fn config(&self) -> &config::DnsBenchConfig {
+        &self.config
     }
 
     /// Save the configuration to a file.
@@ -139,97 +265,272 @@ impl BenchmarkRunner {
                 self.config.lookup_ip,
                 self.config.style,
             );
+            if self.config.schedule != Schedule::Sequential || self.config.request_delay > 0 {
+                println!(
+                    "Schedule: {}; Request delay: {} ms",
+                    self.config.schedule, self.config.request_delay
+                );
+            }
+            if self.config.qps.is_some() || self.config.interval.is_some() {
+                println!(
+                    "Pacing per server: {} QPS; Interval: {} ms",
+                    self.config
+                        .qps
+                        .map_or(String::from("unlimited"), |qps| qps.to_string()),
+                    self.config.interval.unwrap_or_default()
+                );
+            }
+            if let Some(bind) = self.config.bind_target() {
+                println!("Bind: {bind}");
+            }
+            if let Some(resolv_conf) = &self.resolv_conf {
+                println!("Profile: {} ({})", self.config.profile, resolv_conf.options);
+            }
+            if self.gateways.len() > 1 {
+                println!("Default gateways:");
+                for (i, gateway) in self.gateways.iter().enumerate() {
+                    let in_use = if i == 0 { ", in use" } else { "" };
+                    println!("  {gateway}{in_use}");
+                }
+            }
+            if !self.system_dns_servers.is_empty() {
+                println!("System DNS servers:");
+                for server in &self.system_dns_servers {
+                    println!("  {server}");
+                }
+            }
         }
     }
 
-    /// Fill the DNS entries with the desired IP version.
-    fn fill_dns_entries(&mut self) {
-        // 1. Get the base list (custom or built-in)
-        let mut entries = match self.config.custom_servers_file.clone() {
-            Some(filepath) => {
-                let custom_entries =
-                    match custom::read_custom_servers_list(filepath, self.config.name_servers_ip) {
-                        Ok(entries) => entries,
-                        Err(e) => {
-                            eprintln!("Failed to read custom servers list: {e:?}");
-                            process::exit(1);
-                        }
-                    };
-                println!("Using custom servers list.");
-                custom_entries
+    /// Append the entries which are not listed yet. The same server may be benchmarked once
+    /// per source of the queries.
+    fn push_new_entries(entries: &mut Vec<servers::DnsEntry>, new_entries: Vec<servers::DnsEntry>) {
+        let mut already_present = entries
+            .iter()
+            .map(|e| (e.socket_addr.ip(), e.bind.clone()))
+            .collect::<collections::HashSet<_>>();
+        for entry in new_entries {
+            if already_present.insert((entry.socket_addr.ip(), entry.bind.clone())) {
+                entries.push(entry);
             }
-            None => match self.config.name_servers_ip {
-                ArgIpAddr::V4 => servers::IPV4_DNS_ENTRIES.clone(),
-                ArgIpAddr::V6 => servers::IPV6_DNS_ENTRIES.clone(),
-            },
-        };
+        }
+    }
 
-        // 2. Try to get gateway DNS and add if not already present
-        if !self.config.skip_gateway_detection {
-            match get_gateway_addr() {
-                Ok(gateway_ip) => {
-                    let is_ip_version_matching = (gateway_ip.is_ipv4()
-                        && self.config.name_servers_ip == ArgIpAddr::V4)
-                        || (gateway_ip.is_ipv6() && self.config.name_servers_ip == ArgIpAddr::V6);
-
-                    if is_ip_version_matching {
-                        let already_present = entries
-                            .iter()
-                            .map(|e| e.socket_addr.ip())
-                            .collect::<collections::HashSet<_>>();
-                        if !already_present.contains(&gateway_ip) {
-                            let socket_addr = SocketAddr::new(gateway_ip, 53);
-                            let resolver = create_resolver(
-                                socket_addr,
-                                self.config.protocol.into(),
-                                GATEWAY_RESPONSIVENESS_TEST_TIMEOUT_MILLIS,
-                                self.config.lookup_ip.into(),
-                            );
-                            // Test if the gateway DNS is responsive by making a simple query
-                            match resolver.lookup_ip("google.com") {
-                                Ok(_) => {
-                                    let name = "Router (Gateway) DNS".to_string();
-                                    entries.push(servers::DnsEntry { name, socket_addr });
-                                }
-                                Err(e) => {
-                                    eprintln!(
-                                        "Gateway DNS at {socket_addr} is not responsive: {e}"
-                                    );
-                                }
-                            }
-                        }
-                    }
+    /// Fill the DNS entries with the desired IP version.
+    fn fill_dns_entries(&mut self) {
+        // 1. Get the base list (built-in and/or custom)
+        let mut entries = Vec::new();
+        let bootstrap = BootstrapResolver::new(
+            self.config.bootstrap_server,
+            self.config.timeout.saturating_mul(1000),
+        );
+        if !self.config.no_builtin
+            && (self.config.custom_servers_files.is_empty() || self.config.merge_custom_servers)
+        {
+            entries.extend(
+                match self.config.name_servers_ip {
+                    ArgIpAddr::V4 => servers::IPV4_DNS_ENTRIES.clone(),
+                    ArgIpAddr::V6 => servers::IPV6_DNS_ENTRIES.clone(),
                 }
+                .into_iter()
+                .map(|mut entry| {
+                    entry
+                        .socket_addr
+                        .set_port(self.config.protocol.default_port());
+                    entry
+                }),
+            );
+        }
+        for filepath in self.config.custom_servers_files.clone() {
+            let custom_entries = match custom::read_custom_servers_list(
+                filepath.clone(),
+                self.config.name_servers_ip,
+                self.config.protocol,
+                &bootstrap,
+            ) {
+                Ok(entries) => entries,
                 Err(e) => {
-                    eprintln!("Failed to detect gateway IP address: {e}");
+                    eprintln!(
+                        "Failed to read custom servers list {}: {e}",
+                        filepath.display()
+                    );
+                    process::exit(1);
                 }
+            };
+            // Status goes to stderr, stdout of export-config and servers list is their output.
+            eprintln!("Using custom servers list {}.", filepath.display());
+
+            Self::push_new_entries(&mut entries, custom_entries);
+        }
+
+        // 2. Add the ad-hoc servers from the command line
+        for server in &self.config.servers {
+            let server_entries = server.to_dns_entries(
+                self.config.protocol.default_port(),
+                self.config.name_servers_ip,
+                &bootstrap,
+            );
+            if server_entries.is_empty() {
+                eprintln!(
+                    "Skipping server {server}: it has no address matching the name servers IP version."
+                );
+                continue;
             }
+            Self::push_new_entries(&mut entries, server_entries);
         }
 
-        // 3. Add system DNS servers if available and not already present
-        if let Some(system_ips) = &self.system_dns_ips {
-            let mut already_present = entries
+        // 3. Apply the include and exclude filters to the listed servers
+        let filter = self.config.dns_entry_filter();
+        entries.retain(|entry| filter.matches(entry));
+
+        // 4. Add the DNS of every responsive gateway if not already present or excluded by name or IP
+        let has_several_gateways = self.gateways.len() > 1;
+        for (i, gateway) in self.gateways.iter().enumerate() {
+            // The same router may be reachable through several interfaces
+            let socket_addr = gateway.socket_addr(self.config.protocol.default_port());
+            let bind = gateway.bind_target();
+            if entries
                 .iter()
-                .map(|e| e.socket_addr.ip())
-                .collect::<collections::HashSet<_>>();
-            for sys_ip in system_ips {
-                let is_ip_version_matching = (sys_ip.is_ipv4()
-                    && self.config.name_servers_ip == ArgIpAddr::V4)
-                    || (sys_ip.is_ipv6() && self.config.name_servers_ip == ArgIpAddr::V6);
-                let is_already_present = already_present.contains(sys_ip);
-
-                if !is_already_present && is_ip_version_matching {
-                    // Add as "System DNS"
-                    let name = "System DNS".to_string();
-                    // Use default port 53
-                    let socket_addr = SocketAddr::new(*sys_ip, 53);
-                    entries.push(servers::DnsEntry { name, socket_addr });
-                    already_present.insert(*sys_ip);
+                .any(|e| e.socket_addr == socket_addr && e.bind == bind)
+            {
+                self.skipped_gateways.push(SkippedGateway::new(
+                    gateway,
+                    GatewaySkipReason::AlreadyPresent,
+                    None,
+                ));
+                continue;
+            }
+            // The OS uses the gateway with the lowest metric, the first one
+            let in_use = has_several_gateways && i == 0;
+            let entry = servers::DnsEntry {
+                bind,
+                ..servers::DnsEntry::new(gateway.name(in_use), socket_addr)
+            };
+            if !filter.matches_name_or_ip(&entry) {
+                continue;
+            }
+
+            let bind_addr = entry
+                .bind
+                .clone()
+                .or(self.config.bind_target())
+                .and_then(|target| target.resolve(gateway.ip.into()).ok());
+            let resolver = create_resolver(
+                socket_addr,
+                self.config.protocol.into(),
+                None,
+                bind_addr,
+                self.config.gateway_probe_timeout_millis(),
+                self.config.lookup_ip.into(),
+            );
+            // Test if the gateway DNS is responsive by making a simple query
+            match resolver.lookup_ip(self.config.gateway_probe_domain()) {
+                Ok(_) => entries.push(entry),
+                Err(e) => {
+                    eprintln!("Gateway DNS at {socket_addr} is not responsive: {e}");
+                    self.skipped_gateways.push(SkippedGateway::new(
+                        gateway,
+                        GatewaySkipReason::NotResponsive,
+                        Some(e.to_string()),
+                    ));
                 }
             }
         }
 
-        // 4. Store entries
+        // 5. Add system DNS servers if available and not already present
+        let mut already_present = entries
+            .iter()
+            .map(|e| e.socket_addr.ip())
+            .collect::<collections::HashSet<_>>();
+        for server in &self.system_dns_servers {
+            let is_ip_version_matching = (server.ip.is_ipv4()
+                && self.config.name_servers_ip == ArgIpAddr::V4)
+                || (server.ip.is_ipv6() && self.config.name_servers_ip == ArgIpAddr::V6);
+            let is_already_present = already_present.contains(&server.ip);
+
+            if !is_already_present && is_ip_version_matching {
+                // Use the default port of the protocol unless the system configures one
+                let socket_addr = server.socket_addr(self.config.protocol.default_port());
+                entries.push(servers::DnsEntry::new(server.name(), socket_addr));
+                already_present.insert(server.ip);
+            }
+        }
+
+        // The system-like profile also benchmarks the resolv.conf name servers together,
+        // failing over between them like glibc.
+        if let Some(resolv_conf) = self.resolv_conf.as_ref() {
+            let socket_addrs = resolv_conf
+                .queried_nameservers()
+                .iter()
+                .map(|server| server.socket_addr(self.config.protocol.default_port()))
+                .collect::<Vec<_>>();
+            if socket_addrs.len() > 1 && !self.config.skip_system_servers {
+                entries.push(servers::DnsEntry {
+                    fallback_addrs: socket_addrs[1..].to_vec(),
+                    ..servers::DnsEntry::new(
+                        String::from("System resolver (resolv.conf)"),
+                        socket_addrs[0],
+                    )
+                });
+            }
+        }
+
+        // 6. Apply the name and IP address filters to the system DNS servers too, they have no
+        // tags to filter by
+        entries.retain(|entry| filter.matches_name_or_ip(entry));
+
+        // 7. Resolve the source of the queries of every server
+        let default_bind = self.config.bind_target();
+        let default_interface = self
+            .gateways
+            .first()
+            .and_then(|gateway| gateway.interface.as_deref());
+        let mut bind_addrs = collections::HashMap::new();
+        entries.retain_mut(|entry| {
+            let Some(target) = entry.bind.clone().or(default_bind.clone()) else {
+                return true;
+            };
+            let ip = entry.socket_addr.ip();
+            let bind_addr = bind_addrs
+                .entry((target.clone(), ip.is_ipv4()))
+                .or_insert_with(|| {
+                    let bind_addr = target.resolve(ip.into());
+                    if let Err(e) = &bind_addr {
+                        eprintln!("Skipping the servers queried from {target}: {e}");
+                    }
+                    // Without binding the sockets to the interface only the source address is
+                    // set, and the queries leave through the default route unless policy
+                    // routing sends them through the interface.
+                    if let (Ok(bind_addr), BindTarget::Interface(name), None, Some(default)) =
+                        (&bind_addr, &target, target.device(), default_interface)
+                    {
+                        if name != default {
+                            eprintln!(
+                                "Warning: the queries from {name} ({bind_addr}) leave through the default route on {default} unless policy routing sends them through {name}."
+                            );
+                        }
+                    }
+                    bind_addr
+                });
+            match bind_addr {
+                Ok(bind_addr) if bind_addr.is_ipv4() == ip.is_ipv4() => {
+                    entry.device = target.device().map(String::from);
+                    entry.bind = Some(BindTarget::Addr(*bind_addr));
+                    true
+                }
+                Ok(bind_addr) => {
+                    eprintln!(
+                        "Skipping server {} ({ip}): it cannot be queried from {bind_addr}.",
+                        entry.name
+                    );
+                    false
+                }
+                Err(_) => false,
+            }
+        });
+
+        // 8. Store entries
+        self.selected_dns_entries.clone_from(&entries);
         self.dns_entries
             .lock()
             .expect(POISONED_MUTEX_ERR)
@@ -242,6 +543,32 @@ impl BenchmarkRunner {
         self.multi_progress = Some(multi_progress);
     }
 
+    /// Stop the benchmark on the first Ctrl-C and keep the results collected so far,
+    /// exit right away on the second one.
+    fn install_interrupt_handler(&self) {
+        let interrupted = self.interrupted.clone();
+        let multi_progress = self.multi_progress.as_ref().unwrap().clone();
+        let installed = ctrlc::set_handler(move || {
+            if interrupted.swap(true, atomic::Ordering::SeqCst) {
+                process::exit(INTERRUPTED_EXIT_CODE);
+            }
+            multi_progress.suspend(|| {
+                eprintln!(
+                    "Interrupted, waiting for the requests in flight to finish. \
+                    Press Ctrl-C again to exit immediately."
+                )
+            });
+        });
+        if let Err(e) = installed {
+            eprintln!("Failed to install the Ctrl-C handler: {e}");
+        }
+    }
+
+    /// Whether the benchmark was interrupted with Ctrl-C.
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(atomic::Ordering::SeqCst)
+    }
+
     /// Initialize a progress bar.
     fn init_progress_bar(requests_count: u64) -> ProgressBar {
         let progress_bar = ProgressBar::new(requests_count);
@@ -260,129 +587,323 @@ impl BenchmarkRunner {
         self.bench_start_time = Some(Instant::now());
     }
 
-    /// Spawn the threads.
-    fn spawn_threads(&mut self) {
+    /// Benchmark the servers on a single async runtime, with at most `threads` servers at a time.
+    fn run_workers(&mut self) {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to create the async runtime");
+        runtime.block_on(self.spawn_workers());
+    }
+
+    /// Spawn the workers, each takes the next server from the queue until it is empty.
+    async fn spawn_workers(&self) {
+        if self.config.schedule != Schedule::Sequential {
+            return self.spawn_interleaved_workers().await;
+        }
+
+        let mut workers = tokio::task::JoinSet::new();
         for _ in 0..self.config.threads {
             let dns_entries = self.dns_entries.clone();
             let result_entries = self.result_entries.clone();
             let config = self.config.clone();
+            let resolv_conf = self.resolv_conf.clone();
             let multi_progress = self.multi_progress.as_ref().unwrap().clone();
+            let interrupted = self.interrupted.clone();
 
-            self.threads.push(thread::spawn(move || loop {
-                let dns_entry = {
-                    let mut dns_entries = dns_entries.lock().expect(POISONED_MUTEX_ERR);
-                    dns_entries.pop_front()
-                };
-
-                if let Some(dns_entry) = dns_entry {
-                    let progress_bar =
-                        multi_progress.add(Self::init_progress_bar(config.requests as u64));
-                    progress_bar.enable_steady_tick(Duration::from_millis(
-                        PROGRESS_BAR_TICK_INTERVAL_MILLIS,
-                    ));
-                    progress_bar.set_message(format!(
-                        "{} ({})",
-                        dns_entry.name,
-                        dns_entry.socket_addr.ip()
-                    ));
+            workers.spawn(async move {
+                loop {
+                    if interrupted.load(atomic::Ordering::SeqCst) {
+                        break;
+                    }
+                    let dns_entry = {
+                        let mut dns_entries = dns_entries.lock().expect(POISONED_MUTEX_ERR);
+                        dns_entries.pop_front()
+                    };
 
-                    let mut measure_results = Vec::new();
-
-                    // Adaptive timeout state
-                    let base_timeout_ms = config.timeout * 1000_u64;
-                    let mut current_timeout_ms = base_timeout_ms;
-                    let mut consecutive_timeout_failures: u32 = 0;
-
-                    for _ in 0..config.requests {
-                        // Create a new resolver for each request with current adaptive timeout.
-                        let resolver = create_resolver(
-                            dns_entry.socket_addr,
-                            config.protocol.into(),
-                            current_timeout_ms,
-                            config.lookup_ip.into(),
-                        );
-
-                        // Measure the time it takes to resolve the domain.
-                        let start_time = Instant::now();
-                        let result_entry: MeasureResult =
-                            match resolver.lookup_ip(config.domain.clone()) {
-                                Ok(response) => {
-                                    let elapsed_time = start_time.elapsed();
-                                    MeasureResult {
-                                        name: dns_entry.name.clone(),
-                                        ip: dns_entry.socket_addr.ip(),
-                                        resolved_ip: response.iter().next().unwrap(),
-                                        time: TimeResult::Succeeded(elapsed_time),
-                                    }
-                                }
-                                Err(e) => MeasureResult {
-                                    name: dns_entry.name.clone(),
-                                    ip: dns_entry.socket_addr.ip(),
-                                    resolved_ip: match config.lookup_ip {
-                                        ArgIpAddr::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-                                        ArgIpAddr::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
-                                    },
-                                    time: TimeResult::Failed(e.to_string()),
-                                },
-                            };
-
-                        if !config.disable_adaptive_timeout {
-                            // Adaptive logic: inspect the result and potentially adjust timeout / abort.
-                            match &result_entry.time {
-                                TimeResult::Succeeded(_) => {
-                                    // Reset failure streak on any success.
-                                    consecutive_timeout_failures = 0;
-                                }
-                                err @ TimeResult::Failed(_) => {
-                                    if err.is_timeout() {
-                                        consecutive_timeout_failures += 1;
-
-                                        // Reduce timeout after 8 consecutive timeouts (if not already reduced).
-                                        if consecutive_timeout_failures
-                                            >= REDUCE_TIMEOUT_AFTER_CONSECUTIVE_FAILURES
-                                            && current_timeout_ms > REDUCED_TIMEOUT_MS
-                                        {
-                                            current_timeout_ms = REDUCED_TIMEOUT_MS;
-                                        }
-
-                                        // Use minimal timeout after 16 consecutive timeouts to speed up remaining requests.
-                                        if consecutive_timeout_failures
-                                            >= MINIMIZE_TIMEOUT_AFTER_CONSECUTIVE_FAILURES
-                                        {
-                                            current_timeout_ms = MINIMAL_TIMEOUT_MS;
-                                        }
-                                    } else {
-                                        // Non-timeout failure does not advance timeout streak (and resets it to avoid accidental decay).
-                                        consecutive_timeout_failures = 0;
-                                    }
-                                }
-                            }
-                        }
+                    if let Some(dns_entry) = dns_entry {
+                        let requests = dns_entry.requests.unwrap_or(config.requests);
+                        let progress_bar =
+                            multi_progress.add(Self::init_progress_bar(requests as u64));
+                        progress_bar.enable_steady_tick(Duration::from_millis(
+                            PROGRESS_BAR_TICK_INTERVAL_MILLIS,
+                        ));
+                        progress_bar.set_message(format!(
+                            "{} ({})",
+                            dns_entry.name,
+                            dns_entry.socket_addr.ip()
+                        ));
 
-                        measure_results.push(result_entry);
-                        progress_bar.inc(1);
+                        let measure_results = Self::bench_server(
+                            &dns_entry,
+                            &config,
+                            resolv_conf.as_ref(),
+                            &interrupted,
+                            &progress_bar,
+                        )
+                        .await;
+
+                        Self::push_result_entry(&result_entries, measure_results, requests);
+
+                        progress_bar.finish_and_clear();
+                        multi_progress.remove(&progress_bar);
+                    } else {
+                        break;
+                    }
+                }
+            });
+        }
+        while let Some(joined) = workers.join_next().await {
+            joined.expect("Benchmark worker panicked");
+        }
+    }
+
+    /// Make the requests to a single server one after another, until all are made or the run is interrupted.
+    async fn bench_server(
+        dns_entry: &servers::DnsEntry,
+        config: &config::DnsBenchConfig,
+        resolv_conf: Option<&ResolvConf>,
+        interrupted: &atomic::AtomicBool,
+        progress_bar: &ProgressBar,
+    ) -> Vec<MeasureResult> {
+        let mut server = ServerRun::new(dns_entry, config, resolv_conf);
+        for request in 0..dns_entry.requests.unwrap_or(config.requests) {
+            // The request in flight is finished, the remaining ones are skipped.
+            if interrupted.load(atomic::Ordering::SeqCst) {
+                break;
+            }
+            if request > 0 {
+                Self::request_delay(config).await;
+            }
+            if let Some(start_at) = server.pacer.reserve() {
+                tokio::time::sleep_until(start_at).await;
+            }
+
+            let resolver = server.resolver(dns_entry, config, resolv_conf, request);
+            let result_entry = Self::measure_request(dns_entry, config, &resolver).await;
+            server.record(result_entry);
+            progress_bar.inc(1);
+        }
+        server.measure_results
+    }
+
+    /// Spawn the workers which spread the requests to all servers over the whole run,
+    /// so that every server is measured under the same network conditions.
+    async fn spawn_interleaved_workers(&self) {
+        let dns_entries = self
+            .dns_entries
+            .lock()
+            .expect(POISONED_MUTEX_ERR)
+            .drain(..)
+            .collect::<Vec<_>>();
+        let requests = dns_entries
+            .iter()
+            .map(|entry| entry.requests.unwrap_or(self.config.requests))
+            .collect::<Vec<_>>();
+
+        let jobs = Self::interleaved_jobs(&requests, self.config.schedule, &mut rand::thread_rng());
+
+        let multi_progress = self.multi_progress.as_ref().unwrap();
+        let progress_bar = multi_progress.add(Self::init_progress_bar(jobs.len() as u64));
+        progress_bar.enable_steady_tick(Duration::from_millis(PROGRESS_BAR_TICK_INTERVAL_MILLIS));
+        progress_bar.set_message(format!(
+            "{} servers, {} schedule",
+            dns_entries.len(),
+            self.config.schedule
+        ));
+
+        let servers = sync::Arc::new(
+            dns_entries
+                .iter()
+                .map(|dns_entry| {
+                    sync::Mutex::new(ServerRun::new(
+                        dns_entry,
+                        &self.config,
+                        self.resolv_conf.as_ref(),
+                    ))
+                })
+                .collect::<Vec<_>>(),
+        );
+        let dns_entries = sync::Arc::new(dns_entries);
+        let jobs = sync::Arc::new(sync::Mutex::new(collections::VecDeque::from(jobs)));
+        // Notified when a server has no request in flight anymore or a worker stops.
+        let idle = sync::Arc::new(tokio::sync::Notify::new());
+        // Spaces the requests of all workers, not of each one.
+        let request_pacer = sync::Arc::new(sync::Mutex::new(Pacer::with_interval(
+            Duration::from_millis(self.config.request_delay),
+        )));
+
+        let mut workers = tokio::task::JoinSet::new();
+        for _ in 0..self.config.threads {
+            let dns_entries = dns_entries.clone();
+            let servers = servers.clone();
+            let jobs = jobs.clone();
+            let idle = idle.clone();
+            let request_pacer = request_pacer.clone();
+            let config = self.config.clone();
+            let resolv_conf = self.resolv_conf.clone();
+            let progress_bar = progress_bar.clone();
+            let interrupted = self.interrupted.clone();
+
+            workers.spawn(async move {
+                loop {
+                    if interrupted.load(atomic::Ordering::SeqCst) {
+                        break;
                     }
+                    let (server, request) = match Self::take_job(&jobs, &servers) {
+                        NextJob::Run(server, request) => (server, request),
+                        NextJob::Wait => {
+                            idle.notified().await;
+                            continue;
+                        }
+                        NextJob::Done => break,
+                    };
 
-                    let result_entry: RawResultEntry = measure_results.into();
-                    result_entries
+                    let not_before = request_pacer
                         .lock()
                         .expect(POISONED_MUTEX_ERR)
-                        .push(result_entry);
+                        .reserve()
+                        .unwrap_or_else(tokio::time::Instant::now);
+                    let (resolver, start_at) = {
+                        let mut server_run = servers[server].lock().expect(POISONED_MUTEX_ERR);
+                        (
+                            server_run.resolver(
+                                &dns_entries[server],
+                                &config,
+                                resolv_conf.as_ref(),
+                                request,
+                            ),
+                            server_run.pacer.reserve_not_before(not_before),
+                        )
+                    };
+                    if let Some(start_at) = start_at {
+                        tokio::time::sleep_until(start_at).await;
+                    }
+                    let result_entry =
+                        Self::measure_request(&dns_entries[server], &config, &resolver).await;
 
-                    progress_bar.finish_and_clear();
-                    multi_progress.remove(&progress_bar);
-                } else {
-                    break;
+                    {
+                        let mut server_run = servers[server].lock().expect(POISONED_MUTEX_ERR);
+                        server_run.record(result_entry);
+                        server_run.in_flight = false;
+                    }
+                    idle.notify_one();
+                    progress_bar.inc(1);
+                }
+                // Let a waiting worker see that the run is over.
+                idle.notify_one();
+            });
+        }
+        while let Some(joined) = workers.join_next().await {
+            joined.expect("Benchmark worker panicked");
+        }
+
+        for (server, requests) in servers.iter().zip(requests) {
+            let measure_results =
+                std::mem::take(&mut server.lock().expect(POISONED_MUTEX_ERR).measure_results);
+            Self::push_result_entry(&self.result_entries, measure_results, requests);
+        }
+
+        progress_bar.finish_and_clear();
+        multi_progress.remove(&progress_bar);
+    }
+
+    /// The requests of an interleaved run as jobs of the index of the server and of the request to
+    /// it, one request to every server in turn, or in random order with the shuffle schedule.
+    fn interleaved_jobs(
+        requests: &[u16],
+        schedule: Schedule,
+        rng: &mut impl rand::Rng,
+    ) -> Vec<(usize, u16)> {
+        let mut jobs = Vec::new();
+        for request in 0..requests.iter().copied().max().unwrap_or_default() {
+            for (server, _) in requests.iter().enumerate().filter(|(_, r)| **r > request) {
+                jobs.push((server, request));
+            }
+        }
+        if schedule == Schedule::Shuffle {
+            jobs.shuffle(rng);
+        }
+        jobs
+    }
+
+    /// Take the first job to a server without a request in flight, so that every server gets a
+    /// single query at a time like in the sequential schedule, and mark the server as busy.
+    fn take_job(
+        jobs: &sync::Mutex<collections::VecDeque<(usize, u16)>>,
+        servers: &[sync::Mutex<ServerRun>],
+    ) -> NextJob {
+        let mut jobs = jobs.lock().expect(POISONED_MUTEX_ERR);
+        if jobs.is_empty() {
+            return NextJob::Done;
+        }
+        for i in 0..jobs.len() {
+            let (server, request) = jobs[i];
+            let mut server_run = servers[server].lock().expect(POISONED_MUTEX_ERR);
+            if !server_run.in_flight {
+                server_run.in_flight = true;
+                jobs.remove(i);
+                return NextJob::Run(server, request);
+            }
+        }
+        NextJob::Wait
+    }
+
+    /// Wait for the configured delay between two requests.
+    async fn request_delay(config: &config::DnsBenchConfig) {
+        if config.request_delay > 0 {
+            tokio::time::sleep(Duration::from_millis(config.request_delay)).await;
+        }
+    }
+
+    /// Make a single request to the server and measure the time it takes to resolve the domain.
+    async fn measure_request(
+        dns_entry: &servers::DnsEntry,
+        config: &config::DnsBenchConfig,
+        resolver: &BoundAsyncResolver,
+    ) -> MeasureResult {
+        let bind_addr = dns_entry.bind_addr();
+        let start_time = Instant::now();
+        match resolver.lookup_ip(config.domain.clone()).await {
+            Ok(response) => {
+                let elapsed_time = start_time.elapsed();
+                MeasureResult {
+                    name: dns_entry.name.clone(),
+                    ip: dns_entry.socket_addr.ip(),
+                    bind: bind_addr,
+                    resolved_ip: response.iter().next().unwrap(),
+                    time: TimeResult::Succeeded(elapsed_time),
                 }
-            }));
+            }
+            Err(e) => MeasureResult {
+                name: dns_entry.name.clone(),
+                ip: dns_entry.socket_addr.ip(),
+                bind: bind_addr,
+                resolved_ip: match config.lookup_ip {
+                    ArgIpAddr::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                    ArgIpAddr::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+                },
+                time: TimeResult::from_resolve_error(&e),
+            },
         }
     }
 
-    /// Wait for all threads to finish.
-    fn await_threads(&mut self) {
-        for handle in self.threads.drain(..) {
-            handle.join().unwrap();
+    /// Store the results of a server, marked as incomplete when not all requests were made.
+    fn push_result_entry(
+        result_entries: &sync::Mutex<Vec<RawResultEntry>>,
+        measure_results: Vec<MeasureResult>,
+        requests: u16,
+    ) {
+        if measure_results.is_empty() {
+            return;
         }
+        let incomplete = measure_results.len() < usize::from(requests);
+        let mut result_entry: RawResultEntry = measure_results.into();
+        result_entry.incomplete = incomplete;
+        result_entries
+            .lock()
+            .expect(POISONED_MUTEX_ERR)
+            .push(result_entry);
     }
 
     /// Sort result entries by average duration, failed entries are at the end.
@@ -401,6 +922,22 @@ impl BenchmarkRunner {
         });
     }
 
+    /// Recolor result entries with the configured thresholds.
+    fn apply_color_thresholds(&self) {
+        let mut result_entries = self.result_entries.lock().expect(POISONED_MUTEX_ERR);
+        // Entries are sorted, so the first one is the fastest if it succeeded at all.
+        let fastest = result_entries
+            .first()
+            .and_then(|entry| match entry.avg_duration {
+                TimeResult::Succeeded(duration) => Some(duration),
+                TimeResult::Failed(_) => None,
+            });
+        let thresholds = self.config.color_thresholds(fastest);
+        for entry in result_entries.iter_mut() {
+            entry.apply_color_thresholds(&thresholds);
+        }
+    }
+
     /// Print the result.
     fn print_result(&self) {
         let results = self.result_entries.lock().expect(POISONED_MUTEX_ERR);
@@ -408,6 +945,9 @@ impl BenchmarkRunner {
         let ctx = OutputFormatterContext {
             system_dns_ips: self.system_dns_ips.clone(),
             config: self.config.clone(),
+            recommendation: self.recommendation(&results),
+            skipped_gateways: self.skipped_gateways.clone(),
+            incomplete: self.is_interrupted(),
         };
         match formatter.write(&results, ctx, &mut io::stdout()) {
             Ok(()) => {}
@@ -415,11 +955,564 @@ impl BenchmarkRunner {
         }
     }
 
+    /// Write the SVG chart into the configured file.
+    fn write_chart(&self) {
+        let Some(chart_path) = &self.config.chart else {
+            return;
+        };
+
+        let results = self.result_entries.lock().expect(POISONED_MUTEX_ERR);
+        let ctx = OutputFormatterContext {
+            system_dns_ips: self.system_dns_ips.clone(),
+            config: self.config.clone(),
+            recommendation: None,
+            skipped_gateways: self.skipped_gateways.clone(),
+            incomplete: self.is_interrupted(),
+        };
+        let written = fs::File::create(chart_path)
+            .map_err(From::from)
+            .and_then(|mut file| SvgChartOutputFormatter.write(&results, ctx, &mut file));
+        match written {
+            Ok(()) => {
+                if self.config.format == Format::HumanReadable {
+                    println!("Chart saved to {}", chart_path.display());
+                }
+            }
+            Err(e) => eprintln!("Error writing chart: {}", e),
+        }
+    }
+
     /// Print the benchmark time.
     fn print_bench_elapsed_time(&self) {
         if self.config.format == Format::HumanReadable {
             let bench_elapsed_time = self.bench_start_time.unwrap().elapsed();
-            println!("Benchmark completed in {bench_elapsed_time:?}",);
+            if self.is_interrupted() {
+                println!("Benchmark interrupted after {bench_elapsed_time:?}");
+            } else {
+                println!("Benchmark completed in {bench_elapsed_time:?}",);
+            }
+        }
+    }
+}
+
+/// The next job of an interleaved worker.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum NextJob {
+    /// Make the request with the index to the server with the index.
+    Run(usize, u16),
+    /// Every remaining job is to a server with a request in flight.
+    Wait,
+    /// No jobs are left.
+    Done,
+}
+
+/// The state of the requests to a single server.
+struct ServerRun {
+    adaptive_timeout: AdaptiveTimeout,
+    pacer: Pacer,
+    /// The resolver of the previous request, with the timeout and the rotation of the name
+    /// servers it was created with.
+    resolver: Option<(u64, usize, BoundAsyncResolver)>,
+    /// Whether a request to the server is in flight in an interleaved run.
+    in_flight: bool,
+    measure_results: Vec<MeasureResult>,
+}
+
+impl ServerRun {
+    fn new(
+        dns_entry: &servers::DnsEntry,
+        config: &config::DnsBenchConfig,
+        resolv_conf: Option<&ResolvConf>,
+    ) -> Self {
+        Self {
+            adaptive_timeout: AdaptiveTimeout::new(dns_entry, config, resolv_conf),
+            pacer: Pacer::new(dns_entry, config),
+            resolver: None,
+            in_flight: false,
+            measure_results: Vec::new(),
+        }
+    }
+
+    /// The resolver for the next request. It is kept between the requests to the server, so TCP
+    /// and encrypted connections are reused like by a long running client, and created again when
+    /// the adaptive timeout or, with the `rotate` option of resolv.conf, the first name server changes.
+    fn resolver(
+        &mut self,
+        dns_entry: &servers::DnsEntry,
+        config: &config::DnsBenchConfig,
+        resolv_conf: Option<&ResolvConf>,
+        request: u16,
+    ) -> BoundAsyncResolver {
+        let timeout_millis = self.adaptive_timeout.timeout_millis();
+        let rotation = match resolv_conf {
+            Some(resolv_conf) if resolv_conf.options.rotate => {
+                usize::from(request) % (dns_entry.fallback_addrs.len() + 1)
+            }
+            _ => 0,
+        };
+        if let Some((created_timeout_millis, created_rotation, resolver)) = &self.resolver {
+            if *created_timeout_millis == timeout_millis && *created_rotation == rotation {
+                return resolver.clone();
+            }
+        }
+
+        let protocol = dns_entry.protocol.unwrap_or(config.protocol).into();
+        let tls_dns_name = dns_entry
+            .tls_dns_name
+            .clone()
+            .or(dns_entry.hostname.clone());
+        let resolver = match resolv_conf {
+            Some(resolv_conf) => {
+                let mut socket_addrs = vec![dns_entry.socket_addr];
+                socket_addrs.extend(&dns_entry.fallback_addrs);
+                socket_addrs.rotate_left(rotation);
+                create_system_like_resolver(
+                    &socket_addrs,
+                    protocol,
+                    tls_dns_name,
+                    dns_entry.query_source(),
+                    timeout_millis,
+                    resolv_conf,
+                    config.lookup_ip.into(),
+                )
+            }
+            None => create_async_resolver(
+                dns_entry.socket_addr,
+                protocol,
+                tls_dns_name,
+                dns_entry.query_source(),
+                timeout_millis,
+                config.lookup_ip.into(),
+            ),
+        };
+        self.resolver = Some((timeout_millis, rotation, resolver.clone()));
+        resolver
+    }
+
+    fn record(&mut self, result_entry: MeasureResult) {
+        self.adaptive_timeout.record(&result_entry.time);
+        self.measure_results.push(result_entry);
+    }
+}
+
+/// Spaces the starts of the requests to a server to stay below its rate limit, or of all
+/// requests of an interleaved run by the request delay.
+struct Pacer {
+    interval: Duration,
+    next_request_at: Option<tokio::time::Instant>,
+}
+
+impl Pacer {
+    fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_request_at: None,
+        }
+    }
+
+    fn new(dns_entry: &servers::DnsEntry, config: &config::DnsBenchConfig) -> Self {
+        let interval = dns_entry
+            .interval
+            .or(config.interval)
+            .map(Duration::from_millis)
+            .unwrap_or_default();
+        let qps_interval = dns_entry
+            .qps
+            .or(config.qps)
+            .and_then(|qps| Duration::from_secs(1).checked_div(qps))
+            .unwrap_or_default();
+        Self::with_interval(interval.max(qps_interval))
+    }
+
+    /// Reserve the start of the next request, the time to wait for when it is not now.
+    fn reserve(&mut self) -> Option<tokio::time::Instant> {
+        self.reserve_not_before(tokio::time::Instant::now())
+    }
+
+    /// Reserve the start of the next request at `not_before` or later, the time to wait for
+    /// when it is not now.
+    fn reserve_not_before(
+        &mut self,
+        not_before: tokio::time::Instant,
+    ) -> Option<tokio::time::Instant> {
+        let now = tokio::time::Instant::now();
+        let start_at = if self.interval.is_zero() {
+            not_before
+        } else {
+            let start_at = self
+                .next_request_at
+                .map_or(not_before, |next| next.max(not_before));
+            self.next_request_at = Some(start_at + self.interval);
+            start_at
+        };
+        (start_at > now).then_some(start_at)
+    }
+}
+
+/// Shortens the timeout of a server after consecutive timeouts.
+struct AdaptiveTimeout {
+    current_timeout_ms: u64,
+    consecutive_timeout_failures: u32,
+    enabled: bool,
+}
+
+impl AdaptiveTimeout {
+    fn new(
+        dns_entry: &servers::DnsEntry,
+        config: &config::DnsBenchConfig,
+        resolv_conf: Option<&ResolvConf>,
+    ) -> Self {
+        let base_timeout_ms = dns_entry
+            .timeout
+            .or(resolv_conf.map(|conf| conf.options.timeout))
+            .unwrap_or(config.timeout)
+            * 1000_u64;
+        Self {
+            current_timeout_ms: base_timeout_ms,
+            consecutive_timeout_failures: 0,
+            // glibc always waits for the full timeout.
+            enabled: !config.disable_adaptive_timeout && resolv_conf.is_none(),
         }
     }
+
+    fn timeout_millis(&self) -> u64 {
+        self.current_timeout_ms
+    }
+
+    /// Inspect the result of a request and potentially adjust the timeout.
+    fn record(&mut self, time: &TimeResult) {
+        if !self.enabled {
+            return;
+        }
+        match time {
+            TimeResult::Succeeded(_) => {
+                // Reset failure streak on any success.
+                self.consecutive_timeout_failures = 0;
+            }
+            err @ TimeResult::Failed(_) => {
+                if err.is_timeout() {
+                    self.consecutive_timeout_failures += 1;
+
+                    // Reduce timeout after 8 consecutive timeouts (if not already reduced).
+                    if self.consecutive_timeout_failures
+                        >= REDUCE_TIMEOUT_AFTER_CONSECUTIVE_FAILURES
+                        && self.current_timeout_ms > REDUCED_TIMEOUT_MS
+                    {
+                        self.current_timeout_ms = REDUCED_TIMEOUT_MS;
+                    }
+
+                    // Use minimal timeout after 16 consecutive timeouts to speed up remaining requests.
+                    if self.consecutive_timeout_failures
+                        >= MINIMIZE_TIMEOUT_AFTER_CONSECUTIVE_FAILURES
+                    {
+                        self.current_timeout_ms = MINIMAL_TIMEOUT_MS;
+                    }
+                } else {
+                    // Non-timeout failure does not advance timeout streak (and resets it to avoid accidental decay).
+                    self.consecutive_timeout_failures = 0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::Protocol;
+    use hickory_resolver::proto::op::Message;
+    use hickory_resolver::proto::op::MessageType;
+    use hickory_resolver::proto::rr::rdata::A;
+    use hickory_resolver::proto::rr::RData;
+    use hickory_resolver::proto::rr::Record;
+    use rand::SeedableRng;
+    use std::io::Read;
+    use std::io::Write;
+    use std::net::SocketAddr;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn answer(query: &[u8]) -> Vec<u8> {
+        let mut message = Message::from_vec(query).unwrap();
+        let name = message.queries()[0].name().clone();
+        message.set_message_type(MessageType::Response);
+        message.add_answer(Record::from_rdata(
+            name,
+            60,
+            RData::A(A(Ipv4Addr::new(192, 0, 2, 1))),
+        ));
+        message.to_vec().unwrap()
+    }
+
+    /// A TCP name server answering every query, with the peers of the connections it accepted.
+    fn spawn_tcp_name_server() -> (SocketAddr, sync::Arc<sync::Mutex<Vec<SocketAddr>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let socket_addr = listener.local_addr().unwrap();
+        let connections = sync::Arc::new(sync::Mutex::new(Vec::new()));
+        let accepted = connections.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                accepted.lock().unwrap().push(stream.peer_addr().unwrap());
+                thread::spawn(move || {
+                    let mut len = [0; 2];
+                    while stream.read_exact(&mut len).is_ok() {
+                        let mut query = vec![0; usize::from(u16::from_be_bytes(len))];
+                        stream.read_exact(&mut query).unwrap();
+                        let response = answer(&query);
+                        stream
+                            .write_all(&(response.len() as u16).to_be_bytes())
+                            .unwrap();
+                        stream.write_all(&response).unwrap();
+                    }
+                });
+            }
+        });
+        (socket_addr, connections)
+    }
+
+    fn tcp_dns_entry(socket_addr: SocketAddr) -> servers::DnsEntry {
+        servers::DnsEntry {
+            protocol: Some(Protocol::Tcp),
+            requests: Some(5),
+            ..servers::DnsEntry::new(String::from("Local"), socket_addr)
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_bench_server_reuses_connection() {
+        let (socket_addr, connections) = spawn_tcp_name_server();
+        let dns_entry = tcp_dns_entry(socket_addr);
+        let config = config::DnsBenchConfig::default();
+
+        let measure_results = block_on(BenchmarkRunner::bench_server(
+            &dns_entry,
+            &config,
+            None,
+            &atomic::AtomicBool::new(false),
+            &ProgressBar::hidden(),
+        ));
+
+        assert_eq!(measure_results.len(), 5);
+        assert!(measure_results
+            .iter()
+            .all(|result| result.time.is_succeeded()
+                && result.resolved_ip == Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(connections.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_bench_server_interrupted() {
+        let (socket_addr, connections) = spawn_tcp_name_server();
+        let dns_entry = tcp_dns_entry(socket_addr);
+        let config = config::DnsBenchConfig::default();
+
+        let measure_results = block_on(BenchmarkRunner::bench_server(
+            &dns_entry,
+            &config,
+            None,
+            &atomic::AtomicBool::new(true),
+            &ProgressBar::hidden(),
+        ));
+
+        assert!(measure_results.is_empty());
+        assert_eq!(connections.lock().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_bench_server_tcp_bind_addr() {
+        let (socket_addr, connections) = spawn_tcp_name_server();
+        let bind_addr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2));
+        let dns_entry = servers::DnsEntry {
+            bind: Some(BindTarget::Addr(bind_addr)),
+            ..tcp_dns_entry(socket_addr)
+        };
+        let config = config::DnsBenchConfig::default();
+
+        let measure_results = block_on(BenchmarkRunner::bench_server(
+            &dns_entry,
+            &config,
+            None,
+            &atomic::AtomicBool::new(false),
+            &ProgressBar::hidden(),
+        ));
+
+        assert!(measure_results
+            .iter()
+            .all(|result| result.time.is_succeeded() && result.bind == Some(bind_addr)));
+        let connections = connections.lock().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].ip(), bind_addr);
+    }
+
+    #[test]
+    fn test_server_run_resolver_recreated_with_timeout() {
+        let (socket_addr, connections) = spawn_tcp_name_server();
+        let dns_entry = tcp_dns_entry(socket_addr);
+        let config = config::DnsBenchConfig::default();
+        let mut server = ServerRun::new(&dns_entry, &config, None);
+
+        block_on(async {
+            for request in 0..2 {
+                let resolver = server.resolver(&dns_entry, &config, None, request);
+                let result = BenchmarkRunner::measure_request(&dns_entry, &config, &resolver).await;
+                assert!(result.time.is_succeeded());
+            }
+            assert_eq!(connections.lock().unwrap().len(), 1);
+
+            server.adaptive_timeout.current_timeout_ms = REDUCED_TIMEOUT_MS;
+            let resolver = server.resolver(&dns_entry, &config, None, 2);
+            let result = BenchmarkRunner::measure_request(&dns_entry, &config, &resolver).await;
+            assert!(result.time.is_succeeded());
+            assert_eq!(connections.lock().unwrap().len(), 2);
+        });
+    }
+
+    #[test]
+    fn test_interleaved_jobs_round_robin() {
+        let jobs = BenchmarkRunner::interleaved_jobs(
+            &[2, 3, 1],
+            Schedule::RoundRobin,
+            &mut rand::thread_rng(),
+        );
+
+        assert_eq!(jobs, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_interleaved_jobs_shuffle() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut jobs = BenchmarkRunner::interleaved_jobs(&[4, 4, 4], Schedule::Shuffle, &mut rng);
+        let round_robin =
+            BenchmarkRunner::interleaved_jobs(&[4, 4, 4], Schedule::RoundRobin, &mut rng);
+
+        assert_ne!(jobs, round_robin);
+        // Every request is made exactly once.
+        jobs.sort();
+        let mut expected = round_robin;
+        expected.sort();
+        assert_eq!(jobs, expected);
+    }
+
+    #[test]
+    fn test_take_job_skips_busy_servers() {
+        let config = config::DnsBenchConfig::default();
+        let servers = ["127.0.0.1:53", "127.0.0.2:53"].map(|socket_addr| {
+            let dns_entry =
+                servers::DnsEntry::new(String::from("Local"), socket_addr.parse().unwrap());
+            sync::Mutex::new(ServerRun::new(&dns_entry, &config, None))
+        });
+        let jobs = sync::Mutex::new(collections::VecDeque::from([(0, 0), (0, 1), (1, 0)]));
+
+        assert_eq!(
+            BenchmarkRunner::take_job(&jobs, &servers),
+            NextJob::Run(0, 0)
+        );
+        // The second request to the first server waits until the first one is finished.
+        assert_eq!(
+            BenchmarkRunner::take_job(&jobs, &servers),
+            NextJob::Run(1, 0)
+        );
+        assert_eq!(BenchmarkRunner::take_job(&jobs, &servers), NextJob::Wait);
+
+        servers[0].lock().unwrap().in_flight = false;
+        assert_eq!(
+            BenchmarkRunner::take_job(&jobs, &servers),
+            NextJob::Run(0, 1)
+        );
+        assert_eq!(BenchmarkRunner::take_job(&jobs, &servers), NextJob::Done);
+    }
+
+    #[test]
+    fn test_pacer_interval() {
+        let dns_entry =
+            servers::DnsEntry::new(String::from("Local"), "127.0.0.1:53".parse().unwrap());
+        let pacer = |interval: Option<u64>, qps: Option<u32>| {
+            let config = config::DnsBenchConfig {
+                interval,
+                qps,
+                ..config::DnsBenchConfig::default()
+            };
+            Pacer::new(&dns_entry, &config).interval
+        };
+
+        assert_eq!(pacer(None, None), Duration::ZERO);
+        assert_eq!(pacer(Some(300), None), Duration::from_millis(300));
+        assert_eq!(pacer(None, Some(4)), Duration::from_millis(250));
+        // The stricter of both limits applies.
+        assert_eq!(pacer(Some(300), Some(10)), Duration::from_millis(300));
+        assert_eq!(pacer(Some(50), Some(10)), Duration::from_millis(100));
+        // A zero rate never reaches the pacer through validation, and leaves the server unpaced.
+        assert_eq!(pacer(None, Some(0)), Duration::ZERO);
+
+        // A server overrides the configured limits.
+        let config = config::DnsBenchConfig {
+            qps: Some(1),
+            ..config::DnsBenchConfig::default()
+        };
+        let dns_entry = servers::DnsEntry {
+            qps: Some(20),
+            ..dns_entry.clone()
+        };
+        assert_eq!(
+            Pacer::new(&dns_entry, &config).interval,
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn test_pacer_reserve() {
+        let dns_entry =
+            servers::DnsEntry::new(String::from("Local"), "127.0.0.1:53".parse().unwrap());
+        let config = config::DnsBenchConfig::default();
+        let mut pacer = Pacer::new(&dns_entry, &config);
+        assert_eq!(pacer.reserve(), None);
+        assert_eq!(pacer.reserve(), None);
+
+        let config = config::DnsBenchConfig {
+            interval: Some(200),
+            ..config::DnsBenchConfig::default()
+        };
+        let mut pacer = Pacer::new(&dns_entry, &config);
+        let before = tokio::time::Instant::now();
+        // The first request starts right away.
+        assert_eq!(pacer.reserve(), None);
+
+        // Back-to-back reservations are spaced by the interval from each other.
+        let second = pacer.reserve().unwrap();
+        let third = pacer.reserve().unwrap();
+        assert!(second >= before + Duration::from_millis(200));
+        assert!(second <= tokio::time::Instant::now() + Duration::from_millis(200));
+        assert_eq!(third, second + Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_pacer_reserve_not_before() {
+        // The request delay of an interleaved run is shared by all workers.
+        let mut request_pacer = Pacer::with_interval(Duration::from_millis(100));
+        let first = request_pacer.reserve();
+        let second = request_pacer.reserve().unwrap();
+        let third = request_pacer.reserve().unwrap();
+        assert_eq!(first, None);
+        assert_eq!(third, second + Duration::from_millis(100));
+
+        // A server without a rate limit starts at the reserved slot of the run.
+        let mut pacer = Pacer::with_interval(Duration::ZERO);
+        assert_eq!(pacer.reserve_not_before(second), Some(second));
+
+        // A rate limited server is spaced from the actual start of its previous request.
+        let mut pacer = Pacer::with_interval(Duration::from_millis(150));
+        assert_eq!(pacer.reserve_not_before(second), Some(second));
+        assert_eq!(
+            pacer.reserve_not_before(third),
+            Some(second + Duration::from_millis(150))
+        );
+    }
 }
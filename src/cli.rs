@@ -1,11 +1,18 @@
+use crate::args::ApplyTarget;
 use crate::args::Format;
+use crate::args::ForwarderTarget;
 use crate::args::IpAddr;
+use crate::args::Profile;
 use crate::args::Protocol;
+use crate::args::Schedule;
+use crate::args::ServersListFormat;
 use crate::args::Style;
+use crate::custom::ServerArg;
 
 use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
+use std::net;
 use std::path::PathBuf;
 
 const HELP_TEMPLATE: &str = "\
@@ -48,13 +55,13 @@ pub struct DefaultArgs {
     pub save_config: bool,
 }
 
-#[derive(Debug, Clone, Args)]
+#[derive(Debug, Clone, Default, Args)]
 pub struct SharedArgs {
     /// The domain to resolve.
     #[arg(long)]
     pub domain: Option<String>,
-    /// The number of threads to use.
-    #[arg(long, value_parser = clap::value_parser!(u16).range(1..256))]
+    /// The number of servers benchmarked concurrently.
+    #[arg(long, visible_alias = "concurrency", value_parser = clap::value_parser!(u16).range(1..4096))]
     pub threads: Option<u16>,
     /// The number of requests to make.
     #[arg(long, value_parser = clap::value_parser!(u16).range(1..1000))]
@@ -74,21 +81,101 @@ pub struct SharedArgs {
     /// The style to use for the table.
     #[arg(long)]
     pub style: Option<Style>,
-    /// Provide a custom list of servers to use instead of the default ones.
+    /// Provide a custom list of servers to use instead of the default ones, can be repeated.
+    #[arg(long = "custom-servers-file", value_name = "FILE")]
+    pub custom_servers_files: Vec<PathBuf>,
+    /// Benchmark the custom servers in addition to the built-in ones, skipping duplicate IP addresses.
+    #[arg(long)]
+    pub merge_custom_servers: bool,
+    /// Add a server as `name=host[:port]` or `host[:port]`, where host is an IP address or host name, can be repeated.
+    #[arg(long = "server", value_name = "[NAME=]HOST[:PORT]")]
+    pub servers: Vec<ServerArg>,
+    /// Do not benchmark the built-in servers.
     #[arg(long)]
-    pub custom_servers_file: Option<PathBuf>,
+    pub no_builtin: bool,
+    /// The name server used to resolve servers given by host name instead of the system resolver.
+    #[arg(long, value_name = "IP")]
+    pub bootstrap_server: Option<net::IpAddr>,
+    /// Only benchmark servers with at least one of the given tags, can be repeated. The detected
+    /// system and gateway DNS servers have no tags and are kept.
+    #[arg(long = "include-tag", value_name = "TAG")]
+    pub include_tags: Vec<String>,
+    /// Skip servers with the given tag, can be repeated.
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    pub exclude_tags: Vec<String>,
+    /// Only benchmark servers with the given name, also the detected system and gateway DNS
+    /// servers, can be repeated.
+    #[arg(long, value_name = "NAME")]
+    pub only: Vec<String>,
+    /// Skip servers with the given name or IP address, also the detected system and gateway DNS
+    /// servers, can be repeated.
+    #[arg(long, value_name = "NAME|IP")]
+    pub exclude: Vec<String>,
     /// The output format.
     #[arg(long)]
     pub format: Option<Format>,
+    /// Write the JSON output as an object with the recommendation instead of a plain array of results.
+    #[arg(long)]
+    pub json_object: bool,
+    /// Write an SVG bar chart of the results to the given file, which must have the .svg extension.
+    #[arg(long, value_name = "FILE")]
+    pub chart: Option<PathBuf>,
+    /// Durations up to this value (in milliseconds) are colored green.
+    #[arg(long, value_name = "MS")]
+    pub good_latency_threshold: Option<u64>,
+    /// Durations up to this value (in milliseconds) are colored yellow, slower ones red.
+    #[arg(long, value_name = "MS")]
+    pub fair_latency_threshold: Option<u64>,
+    /// Success rates (in percent) at or above this value are colored green.
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub good_success_rate_threshold: Option<u8>,
+    /// Success rates (in percent) at or above this value are colored yellow.
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub fair_success_rate_threshold: Option<u8>,
+    /// Success rates (in percent) at or above this value are colored bright red, lower ones red.
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub poor_success_rate_threshold: Option<u8>,
+    /// Compute latency color thresholds relative to the fastest server of the run instead of using fixed values.
+    #[arg(long)]
+    pub relative_latency_thresholds: bool,
     /// Skip autodetection of system DNS servers.
     #[arg(long)]
     pub skip_system_servers: bool,
     /// Skip autodetection of gateway IP address.
     #[arg(long)]
     pub skip_gateway_detection: bool,
+    /// The domain resolved to test whether a gateway answers DNS queries, defaults to the benchmark domain.
+    #[arg(long, value_name = "DOMAIN")]
+    pub gateway_probe_domain: Option<String>,
+    /// The timeout in milliseconds of the gateway probe query, defaults to 200.
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..60000))]
+    pub gateway_probe_timeout: Option<u64>,
     /// Disable adaptive timeout logic (always use the full configured timeout for every request).
     #[arg(long)]
     pub disable_adaptive_timeout: bool,
+    /// The query behavior to benchmark with.
+    #[arg(long)]
+    pub profile: Option<Profile>,
+    /// The order in which the requests to the servers are made.
+    #[arg(long)]
+    pub schedule: Option<Schedule>,
+    /// The delay in milliseconds between two requests to a server in the sequential schedule, and
+    /// between the starts of any two requests of the run in the interleaved schedules.
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(0..60000))]
+    pub request_delay: Option<u64>,
+    /// The maximum number of queries per second to each server.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..10000))]
+    pub qps: Option<u32>,
+    /// The minimum time in milliseconds between the starts of two requests to the same server.
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..60000))]
+    pub interval: Option<u64>,
+    /// Send the queries from the given source address.
+    #[arg(long, value_name = "IP", conflicts_with = "interface")]
+    pub bind: Option<net::IpAddr>,
+    /// Send the queries from the address of the given network interface. On Linux the sockets are
+    /// bound to the interface, elsewhere the OS still picks the route.
+    #[arg(long, value_name = "NAME")]
+    pub interface: Option<String>,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -96,6 +183,13 @@ pub enum Commands {
     /// Commands related to configuration management.
     #[command(subcommand)]
     Config(ConfigCommand),
+    /// Write DNS servers into the system configuration (Linux).
+    Apply(ApplyArgs),
+    /// Run a benchmark and print forwarder configuration for the fastest servers.
+    ExportConfig(ExportConfigArgs),
+    /// Commands related to the benchmarked servers.
+    #[command(subcommand)]
+    Servers(ServersCommand),
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -112,7 +206,7 @@ pub enum ConfigCommand {
     /// List current config values.
     List(ConfigListArgs),
     /// Set one or more config values.
-    Set(ConfigSetArgs),
+    Set(Box<ConfigSetArgs>),
     /// Reset config file to default values.
     Reset(ConfigResetArgs),
     /// Delete config file.
@@ -153,3 +247,92 @@ pub struct ConfigSetArgs {
 empty_command_struct!(ConfigResetArgs);
 
 empty_command_struct!(ConfigDeleteArgs);
+
+#[derive(Debug, Clone, Args)]
+#[command(
+    author = clap::crate_authors!("\n"),
+    version,
+    about,
+    long_about = None,
+    help_template = HELP_TEMPLATE
+)]
+pub struct ApplyArgs {
+    #[command(flatten)]
+    pub common: SharedArgs,
+
+    /// The system configuration to write.
+    #[arg(long, default_value_t = ApplyTarget::ResolvConf)]
+    pub target: ApplyTarget,
+    /// The name server to apply, can be repeated. When omitted, a benchmark is run and the recommended pair is applied.
+    #[arg(long = "nameserver", value_name = "IP")]
+    pub nameservers: Vec<net::IpAddr>,
+    /// The NetworkManager connection to update (required for the network-manager target).
+    #[arg(long)]
+    pub connection: Option<String>,
+    /// Print the changes as a diff without writing anything.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Restore the configuration from the backup created by a previous apply.
+    #[arg(long)]
+    pub restore: bool,
+    /// The root directory system paths are resolved against.
+    #[arg(long, default_value = "/")]
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Clone, Args)]
+#[command(
+    author = clap::crate_authors!("\n"),
+    version,
+    about,
+    long_about = None,
+    help_template = HELP_TEMPLATE
+)]
+pub struct ExportConfigArgs {
+    #[command(flatten)]
+    pub common: SharedArgs,
+
+    /// The forwarder to generate configuration for.
+    #[arg(long)]
+    pub target: ForwarderTarget,
+    /// The number of fastest servers to include.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..))]
+    pub top: u16,
+    /// Write the configuration to a file instead of stdout.
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+#[command(
+    author = clap::crate_authors!("\n"),
+    version,
+    about,
+    long_about = None,
+    help_template = HELP_TEMPLATE
+)]
+pub enum ServersCommand {
+    /// Print the servers a benchmark would run against, without running it.
+    List(ServersListArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+#[command(
+    author = clap::crate_authors!("\n"),
+    version,
+    about,
+    long_about = None,
+    help_template = HELP_TEMPLATE
+)]
+pub struct ServersListArgs {
+    #[command(flatten)]
+    pub common: SharedArgs,
+
+    /// The format of the list, all but the table can be read back with --custom-servers-file.
+    /// The plain format is refused for servers with settings beyond the name and address.
+    #[arg(long, default_value_t = ServersListFormat::Table)]
+    pub list_format: ServersListFormat,
+    /// Write the list to a file instead of stdout.
+    #[arg(long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
@@ -0,0 +1,84 @@
+use crate::apply;
+use crate::apply::ApplyPlan;
+use crate::args::ApplyTarget;
+use crate::bench::BenchmarkRunner;
+use crate::cli::ApplyArgs;
+use crate::cli::DefaultArgs;
+use crate::commands::Command;
+
+#[derive(Debug, Clone)]
+pub struct ApplyCommand;
+
+impl Command<ApplyArgs> for ApplyCommand {
+    fn run(&self, args: ApplyArgs) -> Result<(), Box<dyn std::error::Error>> {
+        let path = apply::target_path(&args.root, args.target, args.connection.as_deref())?;
+
+        if args.restore {
+            if args.dry_run {
+                println!("Would restore {} from backup.", path.display());
+            } else {
+                apply::restore(&path)?;
+                println!("Restored {} from backup.", path.display());
+            }
+            return Ok(());
+        }
+
+        let servers = if args.nameservers.is_empty() {
+            let mut bench = BenchmarkRunner::new(DefaultArgs {
+                args: args.common,
+                save_config: false,
+            });
+            bench.measure();
+            if bench.is_interrupted() {
+                return Err("The benchmark was interrupted, no servers were applied".into());
+            }
+            // Only plain DNS servers on port 53 can be written into the system configuration.
+            let recommendation = bench
+                .applicable_recommendation()
+                .ok_or("No responsive plain DNS servers on port 53 found to apply")?;
+            println!(
+                "Recommended servers: {} ({}), {}",
+                recommendation.primary.name,
+                recommendation.primary.ip,
+                recommendation
+                    .secondary
+                    .as_ref()
+                    .map(|s| format!("{} ({})", s.name, s.ip))
+                    .unwrap_or_else(|| String::from("no secondary")),
+            );
+            [Some(recommendation.primary), recommendation.secondary]
+                .into_iter()
+                .flatten()
+                .map(|server| server.ip)
+                .collect()
+        } else {
+            args.nameservers
+        };
+
+        let plan = ApplyPlan::new(path, args.target, &servers)?;
+        if args.dry_run {
+            print!("{}", plan.diff());
+            return Ok(());
+        }
+
+        match plan.apply()? {
+            Some(backup) => println!(
+                "Updated {} (backup saved to {}).",
+                plan.path.display(),
+                backup.display()
+            ),
+            None => println!("Created {}.", plan.path.display()),
+        }
+        match args.target {
+            ApplyTarget::ResolvConf => {}
+            ApplyTarget::SystemdResolved => {
+                println!("Run `systemctl restart systemd-resolved` to apply the changes.")
+            }
+            ApplyTarget::NetworkManager => println!(
+                "Run `nmcli connection reload` and reactivate the connection to apply the changes."
+            ),
+        }
+
+        Ok(())
+    }
+}
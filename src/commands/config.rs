@@ -44,7 +44,7 @@ impl Command<ConfigSetArgs> for ConfigSetCommand {
             println!("Config file does not exist. Run `dns-bench config init` first.");
         } else {
             let mut config = DnsBenchConfig::try_load_from_file().unwrap_or_default();
-            config.resolve_args(&args.common);
+            config.resolve_args(&args.common)?;
             config.write_into_file()?;
             println!("Config file updated.");
         }
@@ -0,0 +1,39 @@
+use crate::bench::BenchmarkRunner;
+use crate::cli::DefaultArgs;
+use crate::cli::ExportConfigArgs;
+use crate::commands::Command;
+use crate::forwarder::render_forwarder_config;
+
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct ExportConfigCommand;
+
+impl Command<ExportConfigArgs> for ExportConfigCommand {
+    fn run(&self, args: ExportConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+        let mut bench = BenchmarkRunner::new(DefaultArgs {
+            args: args.common,
+            save_config: false,
+        });
+        bench.measure();
+        if bench.is_interrupted() {
+            return Err("The benchmark was interrupted, no configuration was exported".into());
+        }
+
+        let entries = bench.fastest_dns_entries(args.top as usize);
+        if entries.is_empty() {
+            return Err("No responsive DNS servers found to export".into());
+        }
+        let content = render_forwarder_config(args.target, &entries, bench.config().protocol);
+
+        match args.output {
+            Some(path) => {
+                fs::write(&path, content)?;
+                eprintln!("Configuration written to {}", path.display());
+            }
+            None => print!("{content}"),
+        }
+
+        Ok(())
+    }
+}
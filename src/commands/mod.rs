@@ -1,12 +1,18 @@
+mod apply;
 mod bench;
 mod config;
+mod export;
+mod servers;
 
+pub use apply::ApplyCommand;
 pub use bench::BenchmarkRunnerCommand;
 pub use config::ConfigDeleteCommand;
 pub use config::ConfigInitCommand;
 pub use config::ConfigListCommand;
 pub use config::ConfigResetCommand;
 pub use config::ConfigSetCommand;
+pub use export::ExportConfigCommand;
+pub use servers::ServersListCommand;
 
 pub trait Command<A: clap::Args> {
     fn run(&self, args: A) -> Result<(), Box<dyn std::error::Error>>;
@@ -0,0 +1,46 @@
+use crate::args::ServersListFormat;
+use crate::bench::BenchmarkRunner;
+use crate::cli::DefaultArgs;
+use crate::cli::ServersListArgs;
+use crate::commands::Command;
+use crate::custom::write_custom_servers_list;
+use crate::custom::WritableServersFormat;
+use crate::output::render_servers_table;
+
+use std::fs;
+
+#[derive(Debug, Clone)]
+pub struct ServersListCommand;
+
+impl Command<ServersListArgs> for ServersListCommand {
+    fn run(&self, args: ServersListArgs) -> Result<(), Box<dyn std::error::Error>> {
+        let mut bench = BenchmarkRunner::new(DefaultArgs {
+            args: args.common,
+            save_config: false,
+        });
+        let entries = bench.dns_entries();
+        let config = bench.config();
+
+        let format = match args.list_format {
+            ServersListFormat::Table => None,
+            ServersListFormat::Json => Some(WritableServersFormat::Json),
+            ServersListFormat::Toml => Some(WritableServersFormat::Toml),
+            ServersListFormat::Yaml => Some(WritableServersFormat::Yaml),
+            ServersListFormat::Plain => Some(WritableServersFormat::Plain),
+        };
+        let content = match format {
+            Some(format) => write_custom_servers_list(&entries, format, config.protocol)?,
+            None => render_servers_table(&entries, config),
+        };
+
+        match args.output {
+            Some(path) => {
+                fs::write(&path, content)?;
+                eprintln!("Servers list written to {}", path.display());
+            }
+            None => print!("{content}"),
+        }
+
+        Ok(())
+    }
+}
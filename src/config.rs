@@ -1,8 +1,14 @@
 use crate::args::Format;
 use crate::args::IpAddr;
+use crate::args::Profile;
 use crate::args::Protocol;
+use crate::args::Schedule;
 use crate::args::Style;
 use crate::cli::SharedArgs;
+use crate::custom::ServerArg;
+use crate::resolver::BindTarget;
+use crate::result::ColorThresholds;
+use crate::servers::DnsEntryFilter;
 
 use clap::ValueEnum;
 use directories::UserDirs;
@@ -10,10 +16,24 @@ use std::error::Error;
 use std::fmt;
 use std::fs;
 use std::io;
+use std::net;
+use std::ops::RangeInclusive;
 use std::path::PathBuf;
+use std::time::Duration;
 
 const CONFIG_DIR_NAME: &str = ".dns-bench";
 const CONFIG_FILE_NAME: &str = "config.toml";
+/// Gateways are probed one after another before the benchmark, so a silent one must not delay it long.
+const DEFAULT_GATEWAY_PROBE_TIMEOUT_MS: u64 = 200;
+// The values accepted on the command line, the config file is held to the same ranges.
+const THREADS_RANGE: RangeInclusive<u64> = 1..=4095;
+const REQUESTS_RANGE: RangeInclusive<u64> = 1..=999;
+const TIMEOUT_RANGE: RangeInclusive<u64> = 1..=59;
+const GATEWAY_PROBE_TIMEOUT_RANGE: RangeInclusive<u64> = 1..=59999;
+const REQUEST_DELAY_RANGE: RangeInclusive<u64> = 0..=59999;
+const QPS_RANGE: RangeInclusive<u64> = 1..=9999;
+const INTERVAL_RANGE: RangeInclusive<u64> = 1..=59999;
+const SUCCESS_RATE_RANGE: RangeInclusive<u64> = 0..=100;
 const USER_DIRS_ERROR: &str =
     "No valid home directory path could be retrieved from the operating system.";
 
@@ -27,19 +47,95 @@ pub struct DnsBenchConfig {
     pub name_servers_ip: IpAddr,
     pub lookup_ip: IpAddr,
     pub style: Style,
-    pub custom_servers_file: Option<PathBuf>,
+    #[serde(
+        default,
+        alias = "custom_servers_file",
+        deserialize_with = "deserialize_one_or_many"
+    )]
+    pub custom_servers_files: Vec<PathBuf>,
+    #[serde(default)]
+    pub merge_custom_servers: bool,
+    #[serde(default)]
+    pub servers: Vec<ServerArg>,
+    #[serde(default)]
+    pub no_builtin: bool,
+    #[serde(default)]
+    pub bootstrap_server: Option<net::IpAddr>,
     #[serde(default)]
     pub format: Format,
     #[serde(default)]
+    pub json_object: bool,
+    #[serde(default)]
     pub skip_system_servers: bool,
     #[serde(default)]
     pub skip_gateway_detection: bool,
     #[serde(default)]
+    pub gateway_probe_domain: Option<String>,
+    #[serde(default)]
+    pub gateway_probe_timeout: Option<u64>,
+    #[serde(default)]
     pub disable_adaptive_timeout: bool,
+    #[serde(default)]
+    pub profile: Profile,
+    #[serde(default)]
+    pub schedule: Schedule,
+    #[serde(default)]
+    pub request_delay: u64,
+    #[serde(default)]
+    pub qps: Option<u32>,
+    #[serde(default)]
+    pub interval: Option<u64>,
+    #[serde(default)]
+    pub bind: Option<net::IpAddr>,
+    #[serde(default)]
+    pub interface: Option<String>,
+    /// Only taken from the command line, a saved chart path would overwrite the chart of every later run.
+    #[serde(skip)]
+    pub chart: Option<PathBuf>,
+    #[serde(default = "default_good_latency_threshold")]
+    pub good_latency_threshold: u64,
+    #[serde(default = "default_fair_latency_threshold")]
+    pub fair_latency_threshold: u64,
+    #[serde(default = "default_good_success_rate_threshold")]
+    pub good_success_rate_threshold: u8,
+    #[serde(default = "default_fair_success_rate_threshold")]
+    pub fair_success_rate_threshold: u8,
+    #[serde(default = "default_poor_success_rate_threshold")]
+    pub poor_success_rate_threshold: u8,
+    #[serde(default)]
+    pub relative_latency_thresholds: bool,
+    #[serde(default)]
+    pub include_tags: Vec<String>,
+    #[serde(default)]
+    pub exclude_tags: Vec<String>,
+    #[serde(default)]
+    pub only: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
     // WARNING! Addition of the serde default attribute for all new fields is important to ensure backward compatibility
     // with older configuration files that may not have these fields defined.
 }
 
+fn default_good_latency_threshold() -> u64 {
+    ColorThresholds::default().good_latency.as_millis() as u64
+}
+
+fn default_fair_latency_threshold() -> u64 {
+    ColorThresholds::default().fair_latency.as_millis() as u64
+}
+
+fn default_good_success_rate_threshold() -> u8 {
+    ColorThresholds::default().good_success_rate
+}
+
+fn default_fair_success_rate_threshold() -> u8 {
+    ColorThresholds::default().fair_success_rate
+}
+
+fn default_poor_success_rate_threshold() -> u8 {
+    ColorThresholds::default().poor_success_rate
+}
+
 impl Default for DnsBenchConfig {
     fn default() -> Self {
         DnsBenchConfig {
@@ -51,17 +147,43 @@ impl Default for DnsBenchConfig {
             name_servers_ip: IpAddr::V4,
             lookup_ip: IpAddr::V4,
             style: Style::Rounded,
-            custom_servers_file: None,
+            custom_servers_files: Vec::new(),
+            merge_custom_servers: false,
+            servers: Vec::new(),
+            no_builtin: false,
+            bootstrap_server: None,
             format: Format::HumanReadable,
+            json_object: false,
             skip_system_servers: false,
             skip_gateway_detection: false,
+            gateway_probe_domain: None,
+            gateway_probe_timeout: None,
             disable_adaptive_timeout: false,
+            profile: Profile::Default,
+            schedule: Schedule::Sequential,
+            request_delay: 0,
+            qps: None,
+            interval: None,
+            bind: None,
+            interface: None,
+            chart: None,
+            good_latency_threshold: default_good_latency_threshold(),
+            fair_latency_threshold: default_fair_latency_threshold(),
+            good_success_rate_threshold: default_good_success_rate_threshold(),
+            fair_success_rate_threshold: default_fair_success_rate_threshold(),
+            poor_success_rate_threshold: default_poor_success_rate_threshold(),
+            relative_latency_thresholds: false,
+            include_tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            only: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 }
 
 impl DnsBenchConfig {
-    pub fn resolve_args(&mut self, args: &SharedArgs) {
+    /// Override the configuration with the given arguments and validate the result.
+    pub fn resolve_args(&mut self, args: &SharedArgs) -> Result<(), ConfigError> {
         if let Some(domain) = &args.domain {
             self.domain.clone_from(domain);
         }
@@ -86,21 +208,222 @@ impl DnsBenchConfig {
         if let Some(style) = args.style {
             self.style = style;
         }
-        if let Some(custom_servers_file) = &args.custom_servers_file {
-            self.custom_servers_file = fs::canonicalize(custom_servers_file).ok()
+        if !args.custom_servers_files.is_empty() {
+            self.custom_servers_files = args
+                .custom_servers_files
+                .iter()
+                .map(|path| fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+                .collect();
+        }
+        if args.merge_custom_servers {
+            self.merge_custom_servers = true;
+        }
+        if !args.servers.is_empty() {
+            self.servers.clone_from(&args.servers);
+        }
+        if args.no_builtin {
+            self.no_builtin = true;
+        }
+        if let Some(bootstrap_server) = args.bootstrap_server {
+            self.bootstrap_server = Some(bootstrap_server);
         }
         if let Some(format) = args.format {
             self.format = format;
         }
+        if args.json_object {
+            self.json_object = true;
+        }
         if args.skip_system_servers {
             self.skip_system_servers = true;
         }
         if args.skip_gateway_detection {
             self.skip_gateway_detection = true;
         }
+        if let Some(gateway_probe_domain) = &args.gateway_probe_domain {
+            self.gateway_probe_domain = Some(gateway_probe_domain.clone());
+        }
+        if let Some(gateway_probe_timeout) = args.gateway_probe_timeout {
+            self.gateway_probe_timeout = Some(gateway_probe_timeout);
+        }
         if args.disable_adaptive_timeout {
             self.disable_adaptive_timeout = true;
         }
+        if let Some(profile) = args.profile {
+            self.profile = profile;
+        }
+        if let Some(schedule) = args.schedule {
+            self.schedule = schedule;
+        }
+        if let Some(request_delay) = args.request_delay {
+            self.request_delay = request_delay;
+        }
+        if let Some(qps) = args.qps {
+            self.qps = Some(qps);
+        }
+        if let Some(interval) = args.interval {
+            self.interval = Some(interval);
+        }
+        // The source address and interface exclude each other, the last one given wins
+        if let Some(bind) = args.bind {
+            self.bind = Some(bind);
+            self.interface = None;
+        }
+        if let Some(interface) = &args.interface {
+            self.interface = Some(interface.clone());
+            self.bind = None;
+        }
+        if let Some(chart) = &args.chart {
+            self.chart = Some(chart.clone());
+        }
+        if let Some(good_latency_threshold) = args.good_latency_threshold {
+            self.good_latency_threshold = good_latency_threshold;
+        }
+        if let Some(fair_latency_threshold) = args.fair_latency_threshold {
+            self.fair_latency_threshold = fair_latency_threshold;
+        }
+        if let Some(good_success_rate_threshold) = args.good_success_rate_threshold {
+            self.good_success_rate_threshold = good_success_rate_threshold;
+        }
+        if let Some(fair_success_rate_threshold) = args.fair_success_rate_threshold {
+            self.fair_success_rate_threshold = fair_success_rate_threshold;
+        }
+        if let Some(poor_success_rate_threshold) = args.poor_success_rate_threshold {
+            self.poor_success_rate_threshold = poor_success_rate_threshold;
+        }
+        if args.relative_latency_thresholds {
+            self.relative_latency_thresholds = true;
+        }
+        if !args.include_tags.is_empty() {
+            self.include_tags.clone_from(&args.include_tags);
+        }
+        if !args.exclude_tags.is_empty() {
+            self.exclude_tags.clone_from(&args.exclude_tags);
+        }
+        if !args.only.is_empty() {
+            self.only.clone_from(&args.only);
+        }
+        if !args.exclude.is_empty() {
+            self.exclude.clone_from(&args.exclude);
+        }
+
+        self.validate()
+    }
+
+    /// Check that the color thresholds are ordered from good to poor and the chart can be written.
+    fn validate(&self) -> Result<(), ConfigError> {
+        let ranges = [
+            ("threads", Some(u64::from(self.threads)), THREADS_RANGE),
+            ("requests", Some(u64::from(self.requests)), REQUESTS_RANGE),
+            ("timeout", Some(self.timeout), TIMEOUT_RANGE),
+            (
+                "gateway_probe_timeout",
+                self.gateway_probe_timeout,
+                GATEWAY_PROBE_TIMEOUT_RANGE,
+            ),
+            (
+                "request_delay",
+                Some(self.request_delay),
+                REQUEST_DELAY_RANGE,
+            ),
+            ("qps", self.qps.map(u64::from), QPS_RANGE),
+            ("interval", self.interval, INTERVAL_RANGE),
+            (
+                "good_success_rate_threshold",
+                Some(u64::from(self.good_success_rate_threshold)),
+                SUCCESS_RATE_RANGE,
+            ),
+            (
+                "fair_success_rate_threshold",
+                Some(u64::from(self.fair_success_rate_threshold)),
+                SUCCESS_RATE_RANGE,
+            ),
+            (
+                "poor_success_rate_threshold",
+                Some(u64::from(self.poor_success_rate_threshold)),
+                SUCCESS_RATE_RANGE,
+            ),
+        ];
+        for (name, value, range) in ranges {
+            if let Some(value) = value.filter(|value| !range.contains(value)) {
+                return Err(ConfigError::OutOfRange {
+                    name,
+                    value,
+                    min: *range.start(),
+                    max: *range.end(),
+                });
+            }
+        }
+        if self.good_latency_threshold > self.fair_latency_threshold {
+            return Err(ConfigError::LatencyThresholds {
+                good: self.good_latency_threshold,
+                fair: self.fair_latency_threshold,
+            });
+        }
+        if self.good_success_rate_threshold < self.fair_success_rate_threshold
+            || self.fair_success_rate_threshold < self.poor_success_rate_threshold
+        {
+            return Err(ConfigError::SuccessRateThresholds {
+                good: self.good_success_rate_threshold,
+                fair: self.fair_success_rate_threshold,
+                poor: self.poor_success_rate_threshold,
+            });
+        }
+        if let Some(chart) = &self.chart {
+            let is_svg = chart
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+            if !is_svg {
+                return Err(ConfigError::ChartFormat {
+                    path: chart.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the filter of benchmarked servers.
+    pub fn dns_entry_filter(&self) -> DnsEntryFilter {
+        DnsEntryFilter {
+            include_tags: self.include_tags.clone(),
+            exclude_tags: self.exclude_tags.clone(),
+            only: self.only.clone(),
+            exclude: self.exclude.clone(),
+        }
+    }
+
+    /// The domain resolved to test whether a gateway answers DNS queries.
+    pub fn gateway_probe_domain(&self) -> &str {
+        self.gateway_probe_domain.as_deref().unwrap_or(&self.domain)
+    }
+
+    /// The timeout in milliseconds of the gateway probe query.
+    pub fn gateway_probe_timeout_millis(&self) -> u64 {
+        self.gateway_probe_timeout
+            .unwrap_or(DEFAULT_GATEWAY_PROBE_TIMEOUT_MS)
+    }
+
+    /// The source of the queries, the address taking precedence over the interface.
+    pub fn bind_target(&self) -> Option<BindTarget> {
+        self.bind
+            .map(BindTarget::Addr)
+            .or_else(|| self.interface.clone().map(BindTarget::Interface))
+    }
+
+    /// Build the color thresholds, scaled relative to the fastest average duration when requested.
+    pub fn color_thresholds(&self, fastest: Option<Duration>) -> ColorThresholds {
+        let thresholds = ColorThresholds {
+            good_latency: Duration::from_millis(self.good_latency_threshold),
+            fair_latency: Duration::from_millis(self.fair_latency_threshold),
+            good_success_rate: self.good_success_rate_threshold,
+            fair_success_rate: self.fair_success_rate_threshold,
+            poor_success_rate: self.poor_success_rate_threshold,
+        };
+
+        match fastest {
+            Some(fastest) if self.relative_latency_thresholds => thresholds.relative_to(fastest),
+            _ => thresholds,
+        }
     }
 
     pub fn try_load_from_file() -> LoadConfigResult {
@@ -124,7 +447,7 @@ impl DnsBenchConfig {
             Err(e) => return LoadConfigResult::Error(LoadConfigError::Toml(e)),
         };
 
-        LoadConfigResult::Loaded(config)
+        LoadConfigResult::Loaded(Box::new(config))
     }
 
     pub fn write_into_file(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -161,6 +484,36 @@ impl DnsBenchConfig {
     }
 }
 
+/// Accept a single value as written by older versions as well as a list.
+fn deserialize_one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        One(T),
+        Many(Vec<T>),
+    }
+
+    Ok(
+        match <OneOrMany<T> as serde::Deserialize>::deserialize(deserializer)? {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        },
+    )
+}
+
+/// Comma-separated values, or `null` when there are none.
+fn display_list(values: &[String]) -> String {
+    if values.is_empty() {
+        String::from("null")
+    } else {
+        values.join(", ")
+    }
+}
+
 impl fmt::Display for DnsBenchConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "domain: {}", self.domain)?;
@@ -200,10 +553,34 @@ impl fmt::Display for DnsBenchConfig {
                 .get_name()
         )?;
 
-        if let Some(custom_servers_file) = &self.custom_servers_file {
-            writeln!(f, "custom-servers-file: {}", custom_servers_file.display())?;
+        writeln!(
+            f,
+            "custom-servers-file: {}",
+            display_list(
+                &self
+                    .custom_servers_files
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(f, "merge-custom-servers: {}", self.merge_custom_servers)?;
+        writeln!(
+            f,
+            "server: {}",
+            display_list(
+                &self
+                    .servers
+                    .iter()
+                    .map(ServerArg::to_string)
+                    .collect::<Vec<_>>()
+            )
+        )?;
+        writeln!(f, "no-builtin: {}", self.no_builtin)?;
+        if let Some(bootstrap_server) = &self.bootstrap_server {
+            writeln!(f, "bootstrap-server: {bootstrap_server}")?;
         } else {
-            writeln!(f, "custom-servers-file: null")?; // Explicitly show null if not set
+            writeln!(f, "bootstrap-server: null")?;
         }
 
         writeln!(
@@ -214,13 +591,74 @@ impl fmt::Display for DnsBenchConfig {
                 .expect("Failed to get format")
                 .get_name()
         )?;
+        writeln!(f, "json-object: {}", self.json_object)?;
         writeln!(f, "skip-system-servers: {}", self.skip_system_servers)?;
         writeln!(f, "skip-gateway-detection: {}", self.skip_gateway_detection)?;
+        if let Some(gateway_probe_domain) = &self.gateway_probe_domain {
+            writeln!(f, "gateway-probe-domain: {gateway_probe_domain}")?;
+        } else {
+            writeln!(f, "gateway-probe-domain: null")?;
+        }
+        if let Some(gateway_probe_timeout) = self.gateway_probe_timeout {
+            writeln!(f, "gateway-probe-timeout: {gateway_probe_timeout}")?;
+        } else {
+            writeln!(f, "gateway-probe-timeout: null")?;
+        }
         writeln!(
             f,
             "disable-adaptive-timeout: {}",
             self.disable_adaptive_timeout
         )?;
+        writeln!(f, "profile: {}", self.profile)?;
+        writeln!(f, "schedule: {}", self.schedule)?;
+        writeln!(f, "request-delay: {}", self.request_delay)?;
+        if let Some(qps) = self.qps {
+            writeln!(f, "qps: {qps}")?;
+        } else {
+            writeln!(f, "qps: null")?;
+        }
+        if let Some(interval) = self.interval {
+            writeln!(f, "interval: {interval}")?;
+        } else {
+            writeln!(f, "interval: null")?;
+        }
+        if let Some(bind) = self.bind {
+            writeln!(f, "bind: {bind}")?;
+        } else {
+            writeln!(f, "bind: null")?;
+        }
+        if let Some(interface) = &self.interface {
+            writeln!(f, "interface: {interface}")?;
+        } else {
+            writeln!(f, "interface: null")?;
+        }
+
+        writeln!(f, "good-latency-threshold: {}", self.good_latency_threshold)?;
+        writeln!(f, "fair-latency-threshold: {}", self.fair_latency_threshold)?;
+        writeln!(
+            f,
+            "good-success-rate-threshold: {}",
+            self.good_success_rate_threshold
+        )?;
+        writeln!(
+            f,
+            "fair-success-rate-threshold: {}",
+            self.fair_success_rate_threshold
+        )?;
+        writeln!(
+            f,
+            "poor-success-rate-threshold: {}",
+            self.poor_success_rate_threshold
+        )?;
+        writeln!(
+            f,
+            "relative-latency-thresholds: {}",
+            self.relative_latency_thresholds
+        )?;
+        writeln!(f, "include-tags: {}", display_list(&self.include_tags))?;
+        writeln!(f, "exclude-tags: {}", display_list(&self.exclude_tags))?;
+        writeln!(f, "only: {}", display_list(&self.only))?;
+        writeln!(f, "exclude: {}", display_list(&self.exclude))?;
 
         Ok(())
     }
@@ -228,7 +666,7 @@ impl fmt::Display for DnsBenchConfig {
 
 #[derive(Debug)]
 pub enum LoadConfigResult {
-    Loaded(DnsBenchConfig),
+    Loaded(Box<DnsBenchConfig>),
     FileDoesNotExist,
     Error(LoadConfigError),
 }
@@ -236,7 +674,7 @@ pub enum LoadConfigResult {
 impl LoadConfigResult {
     pub fn unwrap_or_default(self) -> DnsBenchConfig {
         match self {
-            LoadConfigResult::Loaded(c) => c,
+            LoadConfigResult::Loaded(c) => *c,
             LoadConfigResult::FileDoesNotExist => DnsBenchConfig::default(),
             LoadConfigResult::Error(e) => {
                 eprintln!(
@@ -249,6 +687,54 @@ impl LoadConfigResult {
     }
 }
 
+#[derive(Debug, derive_more::Error)]
+pub enum ConfigError {
+    LatencyThresholds {
+        good: u64,
+        fair: u64,
+    },
+    SuccessRateThresholds {
+        good: u8,
+        fair: u8,
+        poor: u8,
+    },
+    ChartFormat {
+        path: PathBuf,
+    },
+    OutOfRange {
+        name: &'static str,
+        value: u64,
+        min: u64,
+        max: u64,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::LatencyThresholds { good, fair } => write!(
+                f,
+                "The good latency threshold ({good} ms) must not exceed the fair one ({fair} ms)"
+            ),
+            ConfigError::SuccessRateThresholds { good, fair, poor } => write!(
+                f,
+                "The success rate thresholds must be ordered good >= fair >= poor, got {good}% >= {fair}% >= {poor}%"
+            ),
+            ConfigError::ChartFormat { path } => write!(
+                f,
+                "The chart is written as SVG, the file {} must have the .svg extension",
+                path.display()
+            ),
+            ConfigError::OutOfRange {
+                name,
+                value,
+                min,
+                max,
+            } => write!(f, "The {name} ({value}) must be between {min} and {max}"),
+        }
+    }
+}
+
 #[derive(Debug, derive_more::Error, derive_more::From)]
 pub enum LoadConfigError {
     UserDirs,
@@ -265,3 +751,153 @@ impl fmt::Display for LoadConfigError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG_PREFIX: &str = r#"
+        domain = "google.com"
+        threads = 8
+        requests = 25
+        timeout = 3
+        protocol = "Udp"
+        name_servers_ip = "V4"
+        lookup_ip = "V4"
+        style = "Rounded"
+    "#;
+
+    #[test]
+    fn test_deserialize_custom_servers_files() {
+        let config: DnsBenchConfig = toml::from_str(&format!(
+            "{CONFIG_PREFIX}custom_servers_file = \"/etc/dns-bench/servers.txt\"\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.custom_servers_files,
+            [PathBuf::from("/etc/dns-bench/servers.txt")]
+        );
+
+        let config: DnsBenchConfig = toml::from_str(&format!(
+            "{CONFIG_PREFIX}custom_servers_files = [\"a.toml\", \"b.txt\"]\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.custom_servers_files,
+            [PathBuf::from("a.toml"), PathBuf::from("b.txt")]
+        );
+        assert!(!config.merge_custom_servers);
+
+        let config: DnsBenchConfig = toml::from_str(CONFIG_PREFIX).unwrap();
+        assert!(config.custom_servers_files.is_empty());
+        assert_eq!(
+            toml::from_str::<DnsBenchConfig>(&toml::to_string_pretty(&config).unwrap()).unwrap(),
+            config
+        );
+    }
+
+    #[test]
+    fn test_resolve_args_rejects_unordered_thresholds() {
+        let mut config = DnsBenchConfig::default();
+        assert!(config.resolve_args(&SharedArgs::default()).is_ok());
+
+        let args = SharedArgs {
+            good_latency_threshold: Some(100),
+            fair_latency_threshold: Some(50),
+            ..SharedArgs::default()
+        };
+        assert!(matches!(
+            DnsBenchConfig::default().resolve_args(&args),
+            Err(ConfigError::LatencyThresholds {
+                good: 100,
+                fair: 50
+            })
+        ));
+
+        let args = SharedArgs {
+            fair_success_rate_threshold: Some(10),
+            ..SharedArgs::default()
+        };
+        assert!(matches!(
+            DnsBenchConfig::default().resolve_args(&args),
+            Err(ConfigError::SuccessRateThresholds {
+                good: 100,
+                fair: 10,
+                poor: 20
+            })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_args_rejects_non_svg_chart() {
+        for chart in ["results.svg", "results.SVG"] {
+            let args = SharedArgs {
+                chart: Some(PathBuf::from(chart)),
+                ..SharedArgs::default()
+            };
+            assert!(DnsBenchConfig::default().resolve_args(&args).is_ok());
+        }
+
+        for chart in ["results.png", "results"] {
+            let args = SharedArgs {
+                chart: Some(PathBuf::from(chart)),
+                ..SharedArgs::default()
+            };
+            assert!(matches!(
+                DnsBenchConfig::default().resolve_args(&args),
+                Err(ConfigError::ChartFormat { path }) if path == std::path::Path::new(chart)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_resolve_args_rejects_out_of_range_config() {
+        let mut config: DnsBenchConfig =
+            toml::from_str(&format!("{CONFIG_PREFIX}qps = 0\n")).unwrap();
+        let Err(e) = config.resolve_args(&SharedArgs::default()) else {
+            panic!("expected qps = 0 to be rejected");
+        };
+        assert_eq!(e.to_string(), "The qps (0) must be between 1 and 9999");
+
+        // The command line replaces the invalid value of the config file.
+        let args = SharedArgs {
+            qps: Some(5),
+            ..SharedArgs::default()
+        };
+        assert!(config.resolve_args(&args).is_ok());
+
+        for mut config in [
+            DnsBenchConfig {
+                threads: 0,
+                ..DnsBenchConfig::default()
+            },
+            DnsBenchConfig {
+                interval: Some(0),
+                ..DnsBenchConfig::default()
+            },
+            DnsBenchConfig {
+                gateway_probe_timeout: Some(60000),
+                ..DnsBenchConfig::default()
+            },
+        ] {
+            assert!(matches!(
+                config.resolve_args(&SharedArgs::default()),
+                Err(ConfigError::OutOfRange { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_chart_is_not_persisted() {
+        let config = DnsBenchConfig {
+            chart: Some(PathBuf::from("results.svg")),
+            ..DnsBenchConfig::default()
+        };
+        let config_str = toml::to_string_pretty(&config).unwrap();
+        assert!(!config_str.contains("chart"));
+
+        let config: DnsBenchConfig =
+            toml::from_str(&format!("{CONFIG_PREFIX}chart = \"results.svg\"\n")).unwrap();
+        assert_eq!(config.chart, None);
+    }
+}
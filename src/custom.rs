@@ -1,72 +1,1206 @@
 use crate::args::IpAddr;
+use crate::args::Protocol;
+use crate::resolver::BindTarget;
+use crate::resolver::HostResolver;
 use crate::servers::DnsEntry;
+use crate::stamp::DnsStamp;
+use crate::stamp::StampError;
+use crate::stamp::STAMP_PREFIX;
 
-use std::fs::File;
-use std::io::{self, BufRead};
+use std::fmt;
+use std::fs;
+use std::io;
 use std::net;
+use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-pub fn read_custom_servers_list(filepath: PathBuf, ip: IpAddr) -> io::Result<Vec<DnsEntry>> {
-    let file = File::open(filepath)?;
-    let reader = io::BufReader::new(file);
+const DOH_URL_SCHEME: &str = "https://";
+const DOH_URL_PATH: &str = "/dns-query";
+// The values accepted by `--timeout` and `--requests`.
+const TIMEOUT_RANGE: std::ops::RangeInclusive<u64> = 1..=59;
+const REQUESTS_RANGE: std::ops::RangeInclusive<u16> = 1..=999;
+const QPS_RANGE: std::ops::RangeInclusive<u32> = 1..=9999;
+const INTERVAL_RANGE: std::ops::RangeInclusive<u64> = 1..=59999;
+
+#[derive(Debug, derive_more::Error, derive_more::From)]
+pub enum CustomServersError {
+    Io(io::Error),
+    #[from(ignore)]
+    Parse {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    #[from(ignore)]
+    InvalidLines(#[error(not(source))] Vec<InvalidLine>),
+    #[from(ignore)]
+    InvalidServers(#[error(not(source))] Vec<InvalidServer>),
+    #[from(ignore)]
+    Serialize(#[error(not(source))] String),
+    #[from(ignore)]
+    PlainFormatLoss(#[error(not(source))] Vec<String>),
+}
+
+/// A line of the plain custom servers format which could not be parsed.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvalidLine {
+    pub line: usize,
+    pub content: String,
+}
+
+/// A server of the structured custom servers format with an invalid setting.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvalidServer {
+    /// 1-based position in the `servers` list.
+    pub index: usize,
+    /// The line the server starts at, where the format keeps it.
+    pub line: Option<usize>,
+    pub name: String,
+    pub message: String,
+}
+
+impl fmt::Display for CustomServersError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomServersError::Io(e) => write!(f, "IO error: {e}"),
+            CustomServersError::Parse {
+                message,
+                line,
+                column,
+            } => write!(f, "{message} at line {line}, column {column}"),
+            CustomServersError::InvalidLines(invalid_lines) => {
+                write!(
+                    f,
+                    "Invalid lines, expected `name;host`, `name;host:port` or a DNS stamp:"
+                )?;
+                for invalid_line in invalid_lines {
+                    write!(
+                        f,
+                        "\n  line {}: {}",
+                        invalid_line.line, invalid_line.content
+                    )?;
+                }
+                Ok(())
+            }
+            CustomServersError::InvalidServers(invalid_servers) => {
+                write!(f, "Invalid servers:")?;
+                for invalid_server in invalid_servers {
+                    match invalid_server.line {
+                        Some(line) => write!(f, "\n  line {line}, ")?,
+                        None => write!(f, "\n  ")?,
+                    }
+                    write!(
+                        f,
+                        "server {} \"{}\": {}",
+                        invalid_server.index, invalid_server.name, invalid_server.message
+                    )?;
+                }
+                Ok(())
+            }
+            CustomServersError::Serialize(message) => {
+                write!(f, "Failed to write servers list: {message}")
+            }
+            CustomServersError::PlainFormatLoss(servers) => {
+                write!(
+                    f,
+                    "The plain format cannot hold the settings of these servers, use the TOML, JSON or YAML format:"
+                )?;
+                for server in servers {
+                    write!(f, "\n  {server}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The format of a custom servers file, detected by its extension.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CustomServersFormat {
+    /// `name;ip:port` lines, or DNS stamps.
+    Plain,
+    Toml,
+    Json,
+    Yaml,
+    /// DNS stamps under `## name` headings, as in the public resolver lists of dnscrypt-proxy.
+    Markdown,
+}
+
+impl CustomServersFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("toml") => CustomServersFormat::Toml,
+            Some("json") => CustomServersFormat::Json,
+            Some("yaml" | "yml") => CustomServersFormat::Yaml,
+            Some("md") => CustomServersFormat::Markdown,
+            _ => CustomServersFormat::Plain,
+        }
+    }
+}
+
+/// A DNS over HTTPS endpoint such as `https://dns.google/dns-query`.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct DohUrl {
+    url: String,
+    host: String,
+    port: u16,
+}
+
+impl DohUrl {
+    /// The host name or IP address of the URL, without brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl TryFrom<String> for DohUrl {
+    type Error = String;
+
+    fn try_from(url: String) -> Result<Self, Self::Error> {
+        let rest = url
+            .strip_prefix(DOH_URL_SCHEME)
+            .ok_or_else(|| format!("DoH URL must start with {DOH_URL_SCHEME}: {url}"))?;
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        if path != DOH_URL_PATH {
+            return Err(format!(
+                "DoH URL path must be {DOH_URL_PATH}, got \"{path}\": {url}"
+            ));
+        }
+
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed
+                    .split_once(']')
+                    .ok_or_else(|| format!("Invalid IPv6 host in DoH URL: {url}"))?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        if host.is_empty() {
+            return Err(format!("DoH URL has no host: {url}"));
+        }
+        let port = match port {
+            Some(port) => port
+                .parse()
+                .map_err(|_| format!("Invalid port in DoH URL: {url}"))?,
+            None => Protocol::Https.default_port(),
+        };
+
+        Ok(DohUrl {
+            host: host.to_string(),
+            port,
+            url,
+        })
+    }
+}
+
+impl From<DohUrl> for String {
+    fn from(val: DohUrl) -> Self {
+        val.url
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A server of the structured (TOML, JSON or YAML) custom servers format.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServerSpec {
+    pub name: String,
+    /// IPv4 and IPv6 addresses or host names, only addresses of the selected family are benchmarked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<Host>,
+    /// Defaults to the standard port of the protocol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<Protocol>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_name: Option<String>,
+    /// Implies the HTTPS protocol unless another one is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doh_url: Option<DohUrl>,
+    /// Timeout in seconds, defaults to the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Defaults to the configured number of requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Maximum queries per second, defaults to the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qps: Option<u32>,
+    /// Minimum milliseconds between two requests, defaults to the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// Source address or network interface of the queries, defaults to the configured one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<BindTarget>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl ServerSpec {
+    /// Describe a single address server, the port is left out when it is the default of its protocol.
+    pub fn from_dns_entry(entry: &DnsEntry, default_protocol: Protocol) -> Self {
+        let port = entry.socket_addr.port();
+        ServerSpec {
+            name: entry.name.clone(),
+            addresses: vec![Host::Ip(entry.socket_addr.ip())],
+            port: Some(port)
+                .filter(|port| *port != entry.protocol.unwrap_or(default_protocol).default_port()),
+            protocol: entry.protocol,
+            tls_name: entry.tls_dns_name.clone().or(entry.hostname.clone()),
+            doh_url: None,
+            timeout: entry.timeout,
+            requests: entry.requests,
+            qps: entry.qps,
+            interval: entry.interval,
+            tags: entry.tags.clone(),
+            bind: entry.bind.clone(),
+            enabled: true,
+        }
+    }
+
+    /// Expand the server into one entry per address of the selected family.
+    ///
+    /// `default_protocol` determines the port when neither the server nor its DoH URL set one.
+    /// Every invalid setting is reported at once.
+    fn into_dns_entries(
+        self,
+        ip: IpAddr,
+        default_protocol: Protocol,
+        resolver: &dyn HostResolver,
+    ) -> Result<Vec<DnsEntry>, Vec<String>> {
+        let protocol = self
+            .protocol
+            .or(self.doh_url.as_ref().map(|_| Protocol::Https));
+        let doh_url = self.doh_url.filter(|_| protocol == Some(Protocol::Https));
+        let port = self
+            .port
+            .or(doh_url.as_ref().map(DohUrl::port))
+            .unwrap_or(protocol.unwrap_or(default_protocol).default_port());
+        let tls_dns_name = self
+            .tls_name
+            .or(doh_url.as_ref().map(|url| url.host().to_string()));
+
+        let mut errors = Vec::new();
+        if self
+            .timeout
+            .is_some_and(|timeout| !TIMEOUT_RANGE.contains(&timeout))
+        {
+            errors.push(format!(
+                "timeout must be between {} and {} seconds",
+                TIMEOUT_RANGE.start(),
+                TIMEOUT_RANGE.end()
+            ));
+        }
+        if self
+            .requests
+            .is_some_and(|requests| !REQUESTS_RANGE.contains(&requests))
+        {
+            errors.push(format!(
+                "requests must be between {} and {}",
+                REQUESTS_RANGE.start(),
+                REQUESTS_RANGE.end()
+            ));
+        }
+        if self.qps.is_some_and(|qps| !QPS_RANGE.contains(&qps)) {
+            errors.push(format!(
+                "qps must be between {} and {}",
+                QPS_RANGE.start(),
+                QPS_RANGE.end()
+            ));
+        }
+        if self
+            .interval
+            .is_some_and(|interval| !INTERVAL_RANGE.contains(&interval))
+        {
+            errors.push(format!(
+                "interval must be between {} and {} milliseconds",
+                INTERVAL_RANGE.start(),
+                INTERVAL_RANGE.end()
+            ));
+        }
+
+        let mut addresses = self.addresses;
+        if addresses.is_empty() {
+            addresses.extend(doh_url.and_then(|url| url.host().parse::<Host>().ok()));
+        }
+        if addresses.is_empty() {
+            errors.push(String::from("has no addresses"));
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(addresses
+            .iter()
+            .flat_map(|host| host_dns_entries(&self.name, host, port, ip, resolver))
+            .map(|entry| DnsEntry {
+                protocol,
+                tls_dns_name: tls_dns_name.clone(),
+                timeout: self.timeout,
+                requests: self.requests,
+                qps: self.qps,
+                interval: self.interval,
+                tags: self.tags.clone(),
+                bind: self.bind.clone(),
+                ..entry
+            })
+            .collect())
+    }
+}
+
+/// The root of a structured custom servers file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServersFile {
+    pub servers: Vec<ServerSpec>,
+}
+
+/// [`ServersFile`] with the position of every server, TOML keeps it.
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpannedServersFile {
+    servers: Vec<toml::Spanned<ServerSpec>>,
+}
+
+/// Convert a byte offset into a 1-based line and column.
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+/// Remove the " at line X column Y" suffix serde_json and serde_norway append to messages.
+fn strip_location(message: String) -> String {
+    match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    }
+}
+
+/// Parse the servers of a structured file, with the line each server starts at where the
+/// format keeps it.
+fn parse_servers_file(
+    content: &str,
+    format: CustomServersFormat,
+) -> Result<Vec<(ServerSpec, Option<usize>)>, CustomServersError> {
+    let servers_file: ServersFile = match format {
+        CustomServersFormat::Toml => {
+            let servers_file: SpannedServersFile = toml::from_str(content).map_err(|e| {
+                let (line, column) = line_column(content, e.span().map_or(0, |span| span.start));
+                CustomServersError::Parse {
+                    message: e.message().trim_end().to_string(),
+                    line,
+                    column,
+                }
+            })?;
+            return Ok(servers_file
+                .servers
+                .into_iter()
+                .map(|server| {
+                    let (line, _) = line_column(content, server.span().start);
+                    (server.into_inner(), Some(line))
+                })
+                .collect());
+        }
+        CustomServersFormat::Json => {
+            serde_json::from_str(content).map_err(|e| CustomServersError::Parse {
+                line: e.line(),
+                column: e.column(),
+                message: strip_location(e.to_string()),
+            })?
+        }
+        CustomServersFormat::Yaml => serde_norway::from_str(content).map_err(|e| {
+            let (line, column) = e
+                .location()
+                .map_or((1, 1), |location| (location.line(), location.column()));
+            CustomServersError::Parse {
+                message: strip_location(e.to_string()),
+                line,
+                column,
+            }
+        })?,
+        CustomServersFormat::Plain | CustomServersFormat::Markdown => {
+            unreachable!("plain and Markdown lists are parsed line by line")
+        }
+    };
+    Ok(servers_file
+        .servers
+        .into_iter()
+        .map(|server| (server, None))
+        .collect())
+}
+
+/// Read a custom servers list, the format is detected by the file extension.
+///
+/// Host names are resolved with the given resolver.
+pub fn read_custom_servers_list(
+    filepath: PathBuf,
+    ip: IpAddr,
+    default_protocol: Protocol,
+    resolver: &dyn HostResolver,
+) -> Result<Vec<DnsEntry>, CustomServersError> {
+    let format = CustomServersFormat::from_path(&filepath);
+    let content = fs::read_to_string(filepath)?;
+
+    let servers = match format {
+        CustomServersFormat::Plain => {
+            return read_plain_servers_list(&content, ip, default_protocol, resolver)
+        }
+        CustomServersFormat::Markdown => {
+            return read_markdown_servers_list(&content, ip, default_protocol, resolver)
+        }
+        CustomServersFormat::Toml | CustomServersFormat::Json | CustomServersFormat::Yaml => {
+            parse_servers_file(&content, format)?
+        }
+    };
+
+    // All invalid servers are reported at once, as the invalid lines of the plain format.
+    let mut entries = Vec::new();
+    let mut invalid_servers = Vec::new();
+    for (i, (server, line)) in servers.into_iter().enumerate() {
+        if !server.enabled {
+            continue;
+        }
+        let name = server.name.clone();
+        match server.into_dns_entries(ip, default_protocol, resolver) {
+            Ok(server_entries) => entries.extend(server_entries),
+            Err(messages) => {
+                invalid_servers.extend(messages.into_iter().map(|message| InvalidServer {
+                    index: i + 1,
+                    line,
+                    name: name.clone(),
+                    message,
+                }))
+            }
+        }
+    }
+
+    if invalid_servers.is_empty() {
+        Ok(entries)
+    } else {
+        Err(CustomServersError::InvalidServers(invalid_servers))
+    }
+}
+
+/// The custom servers formats a servers list can be written in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum WritableServersFormat {
+    Plain,
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl From<WritableServersFormat> for CustomServersFormat {
+    fn from(format: WritableServersFormat) -> Self {
+        match format {
+            WritableServersFormat::Plain => CustomServersFormat::Plain,
+            WritableServersFormat::Toml => CustomServersFormat::Toml,
+            WritableServersFormat::Json => CustomServersFormat::Json,
+            WritableServersFormat::Yaml => CustomServersFormat::Yaml,
+        }
+    }
+}
+
+/// The settings of an entry which the plain format cannot hold, read back with `default_protocol`.
+fn plain_format_losses(entry: &DnsEntry, default_protocol: Protocol) -> Vec<&'static str> {
+    let protocol = entry.protocol.unwrap_or(default_protocol);
+    [
+        (protocol != default_protocol, "protocol"),
+        (
+            entry.tls_dns_name.is_some() && matches!(protocol, Protocol::Tls | Protocol::Https),
+            "TLS name",
+        ),
+        (entry.timeout.is_some(), "timeout"),
+        (entry.requests.is_some(), "requests"),
+        (entry.bind.is_some(), "bind"),
+        (entry.qps.is_some(), "qps"),
+        (entry.interval.is_some(), "interval"),
+        (!entry.fallback_addrs.is_empty(), "fallback addresses"),
+    ]
+    .into_iter()
+    .filter_map(|(is_lost, setting)| is_lost.then_some(setting))
+    .collect()
+}
+
+/// Write servers in a custom servers format, so the list can be edited and read back.
+///
+/// The plain format keeps only names and socket addresses, so servers with other settings
+/// are rejected.
+pub fn write_custom_servers_list(
+    entries: &[DnsEntry],
+    format: WritableServersFormat,
+    default_protocol: Protocol,
+) -> Result<String, CustomServersError> {
+    let mut servers_file = ServersFile {
+        servers: Vec::new(),
+    };
+    // Consecutive addresses of the same server, as from the built-in list, share one entry.
+    for entry in entries {
+        let server = ServerSpec::from_dns_entry(entry, default_protocol);
+        match servers_file.servers.last_mut() {
+            Some(last)
+                if ServerSpec {
+                    addresses: last.addresses.clone(),
+                    ..server.clone()
+                } == *last =>
+            {
+                last.addresses.extend(server.addresses);
+            }
+            _ => servers_file.servers.push(server),
+        }
+    }
+    let serialize_error = |e: &dyn fmt::Display| CustomServersError::Serialize(e.to_string());
+
+    match format {
+        WritableServersFormat::Plain => {
+            let losses = entries
+                .iter()
+                .filter_map(|entry| {
+                    let losses = plain_format_losses(entry, default_protocol);
+                    (!losses.is_empty()).then(|| {
+                        format!(
+                            "{} ({}): {}",
+                            entry.name,
+                            entry.socket_addr,
+                            losses.join(", ")
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+            if !losses.is_empty() {
+                return Err(CustomServersError::PlainFormatLoss(losses));
+            }
+            Ok(entries
+                .iter()
+                .map(|entry| format!("{};{}\n", entry.name, entry.socket_addr))
+                .collect())
+        }
+        WritableServersFormat::Toml => {
+            toml::to_string(&servers_file).map_err(|e| serialize_error(&e))
+        }
+        WritableServersFormat::Json => serde_json::to_string_pretty(&servers_file)
+            .map(|json| json + "\n")
+            .map_err(|e| serialize_error(&e)),
+        WritableServersFormat::Yaml => {
+            serde_norway::to_string(&servers_file).map_err(|e| serialize_error(&e))
+        }
+    }
+}
+
+/// Read `name;address` or `[name;]sdns://...` lines, skipping blank lines, `#` comments and
+/// addresses of the other family.
+///
+/// All invalid lines are reported at once.
+fn read_plain_servers_list(
+    content: &str,
+    ip: IpAddr,
+    default_protocol: Protocol,
+    resolver: &dyn HostResolver,
+) -> Result<Vec<DnsEntry>, CustomServersError> {
     let mut entries = Vec::new();
+    let mut invalid_lines = Vec::new();
+    let mut unsupported_stamps = 0;
 
-    for line in reader.lines() {
-        let line = line?;
-        let Some((name, socket_addr)) = parse_line(&line, ip) else {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid line"));
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid_line = InvalidLine {
+            line: i + 1,
+            content: line.to_string(),
         };
-        entries.push(DnsEntry { name, socket_addr });
+        if let Some((name, stamp)) = parse_stamp_line(line) {
+            match stamp_dns_entries(name, stamp, ip, default_protocol, resolver) {
+                Ok(stamp_entries) => entries.extend(stamp_entries),
+                Err(e) if e.is_unsupported() => unsupported_stamps += 1,
+                Err(_) => invalid_lines.push(invalid_line),
+            }
+            continue;
+        }
+        match parse_line(line) {
+            Some((name, host, port)) => entries.extend(host_dns_entries(
+                &name,
+                &host,
+                port.unwrap_or(default_protocol.default_port()),
+                ip,
+                resolver,
+            )),
+            None => invalid_lines.push(invalid_line),
+        }
     }
 
-    Ok(entries)
+    warn_unsupported_stamps(unsupported_stamps);
+    if invalid_lines.is_empty() {
+        Ok(entries)
+    } else {
+        Err(CustomServersError::InvalidLines(invalid_lines))
+    }
 }
 
-fn parse_line(line: &str, ip: IpAddr) -> Option<(String, net::SocketAddr)> {
-    let parts: Vec<&str> = line.split(';').collect();
-    if parts.len() != 2 {
-        return None;
+/// Read the DNS stamps of a Markdown resolver list, named after the `## name` heading above them.
+///
+/// Descriptions and other lines are ignored.
+fn read_markdown_servers_list(
+    content: &str,
+    ip: IpAddr,
+    default_protocol: Protocol,
+    resolver: &dyn HostResolver,
+) -> Result<Vec<DnsEntry>, CustomServersError> {
+    let mut entries = Vec::new();
+    let mut invalid_lines = Vec::new();
+    let mut unsupported_stamps = 0;
+    let mut name = None;
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(heading) = line.strip_prefix("## ") {
+            name = Some(heading.trim());
+            continue;
+        }
+        if !line.starts_with(STAMP_PREFIX) {
+            continue;
+        }
+        match stamp_dns_entries(name, line, ip, default_protocol, resolver) {
+            Ok(stamp_entries) => entries.extend(stamp_entries),
+            Err(e) if e.is_unsupported() => unsupported_stamps += 1,
+            Err(_) => invalid_lines.push(InvalidLine {
+                line: i + 1,
+                content: line.to_string(),
+            }),
+        }
     }
 
-    let name = parts[0].to_string();
-    let addr = if ip == IpAddr::V4 {
-        net::SocketAddr::V4(parts[1].parse::<net::SocketAddrV4>().ok()?)
+    warn_unsupported_stamps(unsupported_stamps);
+    if invalid_lines.is_empty() {
+        Ok(entries)
     } else {
-        net::SocketAddr::V6(parts[1].parse::<net::SocketAddrV6>().ok()?)
+        Err(CustomServersError::InvalidLines(invalid_lines))
+    }
+}
+
+fn warn_unsupported_stamps(count: usize) {
+    if count > 0 {
+        eprintln!(
+            "Skipped {count} DNS stamps of unsupported protocols (DNSCrypt, DNS over QUIC, relays) or DoH paths."
+        );
+    }
+}
+
+/// Split `sdns://...` or `name;sdns://...` into the optional name and the stamp.
+fn parse_stamp_line(line: &str) -> Option<(Option<&str>, &str)> {
+    if line.starts_with(STAMP_PREFIX) {
+        return Some((None, line));
+    }
+    let (name, stamp) = line.split_once(';')?;
+    let (name, stamp) = (name.trim(), stamp.trim());
+    (!name.is_empty() && stamp.starts_with(STAMP_PREFIX)).then_some((Some(name), stamp))
+}
+
+/// One entry per address of the stamp's server, named after its host when no name is given.
+fn stamp_dns_entries(
+    name: Option<&str>,
+    stamp: &str,
+    ip: IpAddr,
+    default_protocol: Protocol,
+    resolver: &dyn HostResolver,
+) -> Result<Vec<DnsEntry>, StampError> {
+    let stamp = stamp.parse::<DnsStamp>()?;
+    let name = name.map_or_else(|| stamp.default_name(), str::to_string);
+    stamp
+        .into_server_spec(name, default_protocol)?
+        .into_dns_entries(ip, default_protocol, resolver)
+        .map_err(|messages| StampError::Invalid(messages.join(", ")))
+}
+
+/// Parse `name;address` where the address is `host`, `host:port` or `[ipv6]:port`.
+fn parse_line(line: &str) -> Option<(String, Host, Option<u16>)> {
+    let (name, address) = line.split_once(';')?;
+    let (name, address) = (name.trim(), address.trim());
+    if name.is_empty() || address.contains(';') {
+        return None;
+    }
+
+    let (host, port) = parse_address(address)?;
+
+    Some((name.to_string(), host, port))
+}
+
+/// Parse `host`, `host:port` or `[ipv6]:port`, where host is an IP address or a host name.
+fn parse_address(address: &str) -> Option<(Host, Option<u16>)> {
+    if let Ok(socket_addr) = address.parse::<net::SocketAddr>() {
+        return Some((Host::Ip(socket_addr.ip()), Some(socket_addr.port())));
+    }
+    if let Ok(host) = address.parse::<Host>() {
+        return Some((host, None));
+    }
+    let (host, port) = address.rsplit_once(':')?;
+    Some((host.parse().ok()?, Some(port.parse().ok()?)))
+}
+
+/// The address of a server, either an IP address or a host name resolved at startup.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize)]
+#[serde(into = "String")]
+pub enum Host {
+    Ip(net::IpAddr),
+    Name(String),
+}
+
+impl Host {
+    /// The addresses of the selected family.
+    fn resolve(&self, ip: IpAddr, resolver: &dyn HostResolver) -> Vec<net::IpAddr> {
+        match self {
+            Host::Ip(address) if is_matching_family(address, ip) => vec![*address],
+            Host::Ip(_) => Vec::new(),
+            Host::Name(hostname) => resolver.resolve(hostname, ip),
+        }
+    }
+}
+
+impl FromStr for Host {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(address) = s.parse::<net::IpAddr>() {
+            return Ok(Host::Ip(address));
+        }
+
+        let hostname = s.strip_suffix('.').unwrap_or(s);
+        let is_valid_label = |label: &str| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        // A numeric top-level label means a malformed IPv4 address rather than a host name.
+        let is_numeric_tld = hostname
+            .rsplit('.')
+            .next()
+            .is_some_and(|tld| tld.chars().all(|c| c.is_ascii_digit()));
+        if hostname.len() > 253 || is_numeric_tld || !hostname.split('.').all(is_valid_label) {
+            return Err(format!("Invalid IP address or host name: {s}"));
+        }
+
+        Ok(Host::Name(hostname.to_ascii_lowercase()))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Host {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Parsing in the visitor lets the error point at the invalid element.
+        struct HostVisitor;
+
+        impl serde::de::Visitor<'_> for HostVisitor {
+            type Value = Host;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "an IP address or host name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Host, E> {
+                value.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(HostVisitor)
+    }
+}
+
+impl From<Host> for String {
+    fn from(val: Host) -> Self {
+        val.to_string()
+    }
+}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Host::Ip(address) => address.fmt(f),
+            Host::Name(hostname) => hostname.fmt(f),
+        }
+    }
+}
+
+/// One entry per address of the host, servers given by host name show it next to their name.
+fn host_dns_entries(
+    name: &str,
+    host: &Host,
+    port: u16,
+    ip: IpAddr,
+    resolver: &dyn HostResolver,
+) -> Vec<DnsEntry> {
+    let (name, hostname) = match host {
+        Host::Ip(_) => (name.to_string(), None),
+        Host::Name(hostname) if hostname.eq_ignore_ascii_case(name) => {
+            (hostname.clone(), Some(hostname.clone()))
+        }
+        Host::Name(hostname) => (format!("{name} ({hostname})"), Some(hostname.clone())),
     };
 
-    Some((name, addr))
+    host.resolve(ip, resolver)
+        .into_iter()
+        .map(|address| DnsEntry {
+            hostname: hostname.clone(),
+            ..DnsEntry::new(name.clone(), net::SocketAddr::new(address, port))
+        })
+        .collect()
+}
+
+/// A server given on the command line as `name=host[:port]` or `host[:port]`.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ServerArg {
+    value: String,
+    name: Option<String>,
+    host: Host,
+    port: Option<u16>,
+}
+
+impl ServerArg {
+    /// Named after its host when no name is given, the port defaults to `default_port`.
+    pub fn to_dns_entries(
+        &self,
+        default_port: u16,
+        ip: IpAddr,
+        resolver: &dyn HostResolver,
+    ) -> Vec<DnsEntry> {
+        let name = self.name.clone().unwrap_or_else(|| self.host.to_string());
+        host_dns_entries(
+            &name,
+            &self.host,
+            self.port.unwrap_or(default_port),
+            ip,
+            resolver,
+        )
+    }
+}
+
+impl FromStr for ServerArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, address) = match s.split_once('=') {
+            Some((name, address)) if !name.trim().is_empty() => {
+                (Some(name.trim().to_string()), address.trim())
+            }
+            Some(_) => return Err(format!("Missing server name: {s}")),
+            None => (None, s.trim()),
+        };
+        let (host, port) = parse_address(address).ok_or_else(|| {
+            format!(
+                "Invalid server address, expected `host`, `host:port` or `[ipv6]:port`: {address}"
+            )
+        })?;
+
+        Ok(ServerArg {
+            value: s.to_string(),
+            name,
+            host,
+            port,
+        })
+    }
+}
+
+impl TryFrom<String> for ServerArg {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ServerArg> for String {
+    fn from(val: ServerArg) -> Self {
+        val.value
+    }
+}
+
+impl fmt::Display for ServerArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+pub fn is_matching_family(address: &net::IpAddr, ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4 => address.is_ipv4(),
+        IpAddr::V6 => address.is_ipv6(),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::servers::TAG_NO_LOGGING;
+
+    /// Resolves host names from a fixed table.
+    struct StaticResolver(Vec<(&'static str, Vec<net::IpAddr>)>);
+
+    impl HostResolver for StaticResolver {
+        fn resolve(&self, hostname: &str, ip: IpAddr) -> Vec<net::IpAddr> {
+            self.0
+                .iter()
+                .filter(|(name, _)| *name == hostname)
+                .flat_map(|(_, addresses)| addresses.clone())
+                .filter(|address| is_matching_family(address, ip))
+                .collect()
+        }
+    }
+
+    const NO_HOSTNAMES: StaticResolver = StaticResolver(Vec::new());
 
     #[test]
     fn test_parse_line_ipv4() {
         let line = "Google;8.8.8.8:53";
-        let (name, socket_addr) = parse_line(line, IpAddr::V4).unwrap();
+        let (name, host, port) = parse_line(line).unwrap();
 
         assert_eq!(name, "Google");
-        assert_eq!(socket_addr, "8.8.8.8:53".parse().unwrap());
+        assert_eq!(host, Host::Ip("8.8.8.8".parse().unwrap()));
+        assert_eq!(port, Some(53));
     }
 
     #[test]
     fn test_parse_line_ipv6() {
         let line = "Google;[2001:4860:4860:0:0:0:0:8888]:53";
-        let (name, socket_addr) = parse_line(line, IpAddr::V6).unwrap();
+        let (name, host, port) = parse_line(line).unwrap();
 
         assert_eq!(name, "Google");
         assert_eq!(
-            socket_addr,
-            "[2001:4860:4860:0:0:0:0:8888]:53".parse().unwrap()
+            host,
+            Host::Ip("2001:4860:4860:0:0:0:0:8888".parse().unwrap())
+        );
+        assert_eq!(port, Some(53));
+    }
+
+    #[test]
+    fn test_parse_line_default_port() {
+        assert_eq!(
+            parse_line("Google;8.8.8.8"),
+            Some((
+                String::from("Google"),
+                Host::Ip("8.8.8.8".parse().unwrap()),
+                None
+            ))
+        );
+        assert_eq!(
+            parse_line(" Google ; 2001:4860:4860::8888 "),
+            Some((
+                String::from("Google"),
+                Host::Ip("2001:4860:4860::8888".parse().unwrap()),
+                None
+            ))
+        );
+        assert_eq!(parse_line("Google;8.8.8.8:53;udp"), None);
+        assert_eq!(parse_line(";8.8.8.8"), None);
+        assert_eq!(parse_line("Google"), None);
+    }
+
+    #[test]
+    fn test_parse_line_hostname() {
+        assert_eq!(
+            parse_line("Internal;DNS.internal.corp:5353"),
+            Some((
+                String::from("Internal"),
+                Host::Name(String::from("dns.internal.corp")),
+                Some(5353)
+            ))
+        );
+        assert_eq!(parse_line("Broken;8.8.8.300"), None);
+        assert_eq!(parse_line("Broken;-dns.corp"), None);
+        assert_eq!(parse_line("Broken;dns.corp:port"), None);
+    }
+
+    #[test]
+    fn test_host_dns_entries() {
+        let resolver = StaticResolver(vec![(
+            "dns.google",
+            vec![
+                "8.8.8.8".parse().unwrap(),
+                "8.8.4.4".parse().unwrap(),
+                "2001:4860:4860::8888".parse().unwrap(),
+            ],
+        )]);
+        let host = Host::Name(String::from("dns.google"));
+
+        let entries = host_dns_entries("Google", &host, 853, IpAddr::V4, &resolver);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Google (dns.google)");
+        assert_eq!(entries[0].hostname.as_deref(), Some("dns.google"));
+        assert_eq!(entries[1].socket_addr, "8.8.4.4:853".parse().unwrap());
+
+        let entries = host_dns_entries("dns.google", &host, 53, IpAddr::V6, &resolver);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "dns.google");
+
+        let unknown = Host::Name(String::from("dns.unknown"));
+        assert!(host_dns_entries("Unknown", &unknown, 53, IpAddr::V4, &resolver).is_empty());
+    }
+
+    #[test]
+    fn test_server_arg() {
+        let server = "Internal=10.0.0.53:5353".parse::<ServerArg>().unwrap();
+        let entries = server.to_dns_entries(53, IpAddr::V4, &NO_HOSTNAMES);
+        assert_eq!(entries[0].name, "Internal");
+        assert_eq!(entries[0].socket_addr, "10.0.0.53:5353".parse().unwrap());
+        assert_eq!(server.to_string(), "Internal=10.0.0.53:5353");
+
+        let server = "2001:db8::1".parse::<ServerArg>().unwrap();
+        let entries = server.to_dns_entries(853, IpAddr::V6, &NO_HOSTNAMES);
+        assert_eq!(entries[0].name, "2001:db8::1");
+        assert_eq!(entries[0].socket_addr, "[2001:db8::1]:853".parse().unwrap());
+        assert!(server
+            .to_dns_entries(853, IpAddr::V4, &NO_HOSTNAMES)
+            .is_empty());
+
+        let resolver = StaticResolver(vec![("resolver.local", vec!["10.0.0.1".parse().unwrap()])]);
+        let server = "resolver.local:5353".parse::<ServerArg>().unwrap();
+        let entries = server.to_dns_entries(53, IpAddr::V4, &resolver);
+        assert_eq!(entries[0].name, "resolver.local");
+        assert_eq!(entries[0].socket_addr, "10.0.0.1:5353".parse().unwrap());
+
+        assert!("=10.0.0.53".parse::<ServerArg>().is_err());
+        assert!("Internal=resolver..local".parse::<ServerArg>().is_err());
+    }
+
+    #[test]
+    fn test_read_plain_servers_list() {
+        let content = "# Public resolvers\n\
+            \n\
+            Google;8.8.8.8\n\
+            Google;2001:4860:4860::8888\n\
+            \x20 # Local resolver\n\
+            Local;[::1]:5353\n\
+            Local;127.0.0.1:5353\n";
+
+        let entries =
+            read_plain_servers_list(content, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.socket_addr))
+                .collect::<Vec<_>>(),
+            [
+                ("Google", "8.8.8.8:53".parse().unwrap()),
+                ("Local", "127.0.0.1:5353".parse().unwrap()),
+            ]
+        );
+
+        let entries =
+            read_plain_servers_list(content, IpAddr::V6, Protocol::Udp, &NO_HOSTNAMES).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].socket_addr, "[::1]:5353".parse().unwrap());
+    }
+
+    #[test]
+    fn test_read_plain_servers_list_hostname() {
+        let resolver = StaticResolver(vec![(
+            "dns.internal.corp",
+            vec!["10.0.0.53".parse().unwrap(), "10.0.1.53".parse().unwrap()],
+        )]);
+        let content = "Internal;dns.internal.corp:5353
+Missing;dns.missing.corp
+";
+
+        let entries =
+            read_plain_servers_list(content, IpAddr::V4, Protocol::Udp, &resolver).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "Internal (dns.internal.corp)");
+        assert_eq!(entries[0].hostname.as_deref(), Some("dns.internal.corp"));
+        assert_eq!(entries[1].socket_addr, "10.0.1.53:5353".parse().unwrap());
+    }
+
+    #[test]
+    fn test_read_plain_servers_list_stamps() {
+        let content = "sdns://AwMAAAAAAAAABzkuOS45LjkADWRucy5xdWFkOS5uZXQ\n\
+            Quad9;sdns://AAcAAAAAAAAACDkuOS45LjEw\n\
+            Google;8.8.8.8\n\
+            sdns://AQEAAAAAAAAADjIwOC42Ny4yMjAuMjIwIBERERERERERERERERERERERERERERERERERERERERERERGzIuZG5zY3J5cHQtY2VydC5vcGVuZG5zLmNvbQ\n\
+            Broken;sdns://AwEAAAAAAAAABzkuOS45Ljk\n";
+
+        let Err(CustomServersError::InvalidLines(invalid_lines)) =
+            read_plain_servers_list(content, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+        else {
+            panic!("expected invalid lines");
+        };
+        assert_eq!(invalid_lines.len(), 1);
+        assert_eq!(invalid_lines[0].line, 5);
+
+        let content = content.rsplit_once("Broken").unwrap().0;
+        let entries =
+            read_plain_servers_list(content, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.socket_addr, entry.protocol))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "dns.quad9.net",
+                    "9.9.9.9:853".parse().unwrap(),
+                    Some(Protocol::Tls)
+                ),
+                ("Quad9", "9.9.9.10:53".parse().unwrap(), None),
+                ("Google", "8.8.8.8:53".parse().unwrap(), None),
+            ]
+        );
+        assert_eq!(entries[0].tls_dns_name.as_deref(), Some("dns.quad9.net"));
+    }
+
+    #[test]
+    fn test_read_plain_servers_list_invalid_lines() {
+        let content = "Google;8.8.8.8\nGoogle 8.8.4.4\n\nCloudflare;1.1.1.1:99999\n";
+
+        let Err(CustomServersError::InvalidLines(invalid_lines)) =
+            read_plain_servers_list(content, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+        else {
+            panic!("expected invalid lines");
+        };
+        assert_eq!(
+            invalid_lines,
+            [
+                InvalidLine {
+                    line: 2,
+                    content: String::from("Google 8.8.4.4"),
+                },
+                InvalidLine {
+                    line: 4,
+                    content: String::from("Cloudflare;1.1.1.1:99999"),
+                },
+            ]
         );
     }
 
     #[test]
     fn test_read_custom_servers_list_ipv4() {
         let filepath = PathBuf::from("./examples/ipv4-custom-servers-example.txt");
-        let entries = read_custom_servers_list(filepath, IpAddr::V4).unwrap();
+        let entries =
+            read_custom_servers_list(filepath, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES).unwrap();
 
         assert_eq!(entries.len(), 41);
         assert_eq!(entries[0].name, "Google");
@@ -76,7 +1210,8 @@ mod tests {
     #[test]
     fn test_read_custom_servers_list_ipv6() {
         let filepath = PathBuf::from("./examples/ipv6-custom-servers-example.txt");
-        let entries = read_custom_servers_list(filepath, IpAddr::V6).unwrap();
+        let entries =
+            read_custom_servers_list(filepath, IpAddr::V6, Protocol::Udp, &NO_HOSTNAMES).unwrap();
 
         assert_eq!(entries.len(), 27);
         assert_eq!(entries[0].name, "Google");
@@ -85,4 +1220,393 @@ mod tests {
             "[2001:4860:4860:0:0:0:0:8888]:53".parse().unwrap()
         );
     }
+
+    #[test]
+    fn test_read_structured_custom_servers_list() {
+        let resolver = StaticResolver(vec![("dns.quad9.net", vec!["9.9.9.9".parse().unwrap()])]);
+        for filepath in [
+            "./examples/custom-servers-example.toml",
+            "./examples/custom-servers-example.json",
+            "./examples/custom-servers-example.yaml",
+        ] {
+            let entries = read_custom_servers_list(
+                PathBuf::from(filepath),
+                IpAddr::V4,
+                Protocol::Udp,
+                &resolver,
+            )
+            .unwrap();
+            let summary = entries
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.name.as_str(),
+                        entry.socket_addr,
+                        entry.protocol,
+                        entry.tls_dns_name.as_deref(),
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                summary,
+                [
+                    ("Google", "8.8.8.8:53".parse().unwrap(), None, None),
+                    ("Google", "8.8.4.4:53".parse().unwrap(), None, None),
+                    (
+                        "Cloudflare DoT",
+                        "1.1.1.1:853".parse().unwrap(),
+                        Some(Protocol::Tls),
+                        Some("cloudflare-dns.com")
+                    ),
+                    (
+                        "Quad9 DoH",
+                        "9.9.9.9:443".parse().unwrap(),
+                        Some(Protocol::Https),
+                        Some("dns.quad9.net")
+                    ),
+                ],
+                "{filepath}"
+            );
+
+            let entries = read_custom_servers_list(
+                PathBuf::from(filepath),
+                IpAddr::V6,
+                Protocol::Tcp,
+                &resolver,
+            )
+            .unwrap();
+            assert_eq!(entries.len(), 2, "{filepath}");
+            assert_eq!(
+                entries[0].socket_addr,
+                "[2001:4860:4860::8888]:53".parse().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_markdown_servers_list() {
+        let filepath = PathBuf::from("./examples/public-resolvers-example.md");
+        assert_eq!(
+            CustomServersFormat::from_path(&filepath),
+            CustomServersFormat::Markdown
+        );
+
+        let entries =
+            read_custom_servers_list(filepath.clone(), IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+                .unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.name.as_str(), entry.socket_addr, entry.protocol))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    "cloudflare",
+                    "1.0.0.1:443".parse().unwrap(),
+                    Some(Protocol::Https)
+                ),
+                (
+                    "quad9-dot",
+                    "9.9.9.9:853".parse().unwrap(),
+                    Some(Protocol::Tls)
+                ),
+                ("quad9-plain", "9.9.9.10:53".parse().unwrap(), None),
+            ]
+        );
+        assert_eq!(
+            entries[0].tls_dns_name.as_deref(),
+            Some("dns.cloudflare.com")
+        );
+        assert!(entries[2].has_tag(TAG_NO_LOGGING));
+
+        let entries =
+            read_custom_servers_list(filepath, IpAddr::V6, Protocol::Tls, &NO_HOSTNAMES).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].socket_addr,
+            "[2606:4700::1111]:8443".parse().unwrap()
+        );
+        assert_eq!(
+            entries[1].socket_addr,
+            "[2620:fe::10]:5353".parse().unwrap()
+        );
+        assert_eq!(entries[1].protocol, Some(Protocol::Udp));
+    }
+
+    #[test]
+    fn test_write_custom_servers_list() {
+        let entries = [
+            DnsEntry {
+                tags: vec![String::from("anycast")],
+                ..DnsEntry::new(String::from("Google"), "8.8.8.8:53".parse().unwrap())
+            },
+            DnsEntry {
+                tags: vec![String::from("anycast")],
+                ..DnsEntry::new(String::from("Google"), "8.8.4.4:53".parse().unwrap())
+            },
+            DnsEntry {
+                protocol: Some(Protocol::Tls),
+                hostname: Some(String::from("dns.internal.corp")),
+                ..DnsEntry::new(
+                    String::from("Internal (dns.internal.corp)"),
+                    "10.0.0.53:8853".parse().unwrap(),
+                )
+            },
+        ];
+
+        assert_eq!(
+            write_custom_servers_list(&entries[..2], WritableServersFormat::Plain, Protocol::Udp)
+                .unwrap(),
+            "Google;8.8.8.8:53\nGoogle;8.8.4.4:53\n"
+        );
+        // The DoT server would be read back as plain DNS.
+        let Err(CustomServersError::PlainFormatLoss(losses)) =
+            write_custom_servers_list(&entries, WritableServersFormat::Plain, Protocol::Udp)
+        else {
+            panic!("expected the plain format to be rejected");
+        };
+        assert_eq!(
+            losses,
+            ["Internal (dns.internal.corp) (10.0.0.53:8853): protocol"]
+        );
+        assert!(write_custom_servers_list(
+            &entries[2..],
+            WritableServersFormat::Plain,
+            Protocol::Tls
+        )
+        .is_ok());
+
+        let toml = write_custom_servers_list(&entries, WritableServersFormat::Toml, Protocol::Udp)
+            .unwrap();
+        assert!(
+            toml.contains("addresses = [\"8.8.8.8\", \"8.8.4.4\"]"),
+            "{toml}"
+        );
+
+        for format in [
+            WritableServersFormat::Toml,
+            WritableServersFormat::Json,
+            WritableServersFormat::Yaml,
+        ] {
+            let content = write_custom_servers_list(&entries, format, Protocol::Udp).unwrap();
+            let servers = parse_servers_file(&content, format.into())
+                .unwrap()
+                .into_iter()
+                .map(|(server, _)| server)
+                .collect::<Vec<_>>();
+            assert_eq!(servers.len(), 2);
+            assert_eq!(servers[0].port, None);
+            assert_eq!(servers[1].port, Some(8853));
+            assert_eq!(servers[1].tls_name.as_deref(), Some("dns.internal.corp"));
+
+            let read_entries = servers
+                .into_iter()
+                .flat_map(|server| {
+                    server
+                        .into_dns_entries(IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                read_entries
+                    .iter()
+                    .map(|entry| (entry.socket_addr, entry.protocol))
+                    .collect::<Vec<_>>(),
+                entries
+                    .iter()
+                    .map(|entry| (entry.socket_addr, entry.protocol))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_into_dns_entries_default_port() {
+        let server = ServerSpec {
+            name: String::from("Local"),
+            addresses: vec!["127.0.0.1".parse().unwrap()],
+            port: None,
+            protocol: None,
+            tls_name: None,
+            doh_url: None,
+            timeout: None,
+            requests: None,
+            qps: None,
+            interval: None,
+            tags: Vec::new(),
+            bind: None,
+            enabled: true,
+        };
+
+        let entries = server
+            .into_dns_entries(IpAddr::V4, Protocol::Tls, &NO_HOSTNAMES)
+            .unwrap();
+
+        assert_eq!(entries[0].socket_addr, "127.0.0.1:853".parse().unwrap());
+        assert_eq!(entries[0].protocol, None);
+    }
+
+    #[test]
+    fn test_into_dns_entries_overrides() {
+        let content = "[[servers]]\n\
+            name = \"Satellite\"\n\
+            addresses = [\"10.0.0.1\"]\n\
+            protocol = \"tcp\"\n\
+            timeout = 10\n\
+            requests = 5\n\
+            qps = 2\n\
+            bind = \"wwan0\"\n";
+        let (server, _) = parse_servers_file(content, CustomServersFormat::Toml)
+            .unwrap()
+            .remove(0);
+
+        let entries = server
+            .clone()
+            .into_dns_entries(IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+            .unwrap();
+        assert_eq!(entries[0].protocol, Some(Protocol::Tcp));
+        assert_eq!(entries[0].timeout, Some(10));
+        assert_eq!(entries[0].requests, Some(5));
+        assert_eq!(entries[0].qps, Some(2));
+        assert_eq!(
+            entries[0].bind,
+            Some(BindTarget::Interface(String::from("wwan0")))
+        );
+
+        let server = ServerSpec {
+            timeout: Some(0),
+            qps: Some(0),
+            ..server
+        };
+        assert_eq!(
+            server
+                .into_dns_entries(IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+                .err(),
+            Some(vec![
+                String::from("timeout must be between 1 and 59 seconds"),
+                String::from("qps must be between 1 and 9999"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_structured_custom_servers_list_invalid_servers() {
+        let dir = std::env::temp_dir().join(format!("dns-bench-invalid-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let toml = dir.join("servers.toml");
+        fs::write(
+            &toml,
+            "[[servers]]\n\
+            name = \"Satellite\"\n\
+            addresses = [\"10.0.0.1\"]\n\
+            timeout = 60\n\
+            \n\
+            [[servers]]\n\
+            name = \"Google\"\n\
+            addresses = [\"8.8.8.8\"]\n\
+            \n\
+            [[servers]]\n\
+            name = \"Empty\"\n\
+            requests = 0\n",
+        )
+        .unwrap();
+        let Err(e) = read_custom_servers_list(toml, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+        else {
+            panic!("expected invalid servers");
+        };
+        assert_eq!(
+            e.to_string(),
+            "Invalid servers:\n  \
+            line 1, server 1 \"Satellite\": timeout must be between 1 and 59 seconds\n  \
+            line 10, server 3 \"Empty\": requests must be between 1 and 999\n  \
+            line 10, server 3 \"Empty\": has no addresses"
+        );
+
+        let yaml = dir.join("servers.yaml");
+        fs::write(
+            &yaml,
+            "servers:\n  - name: Google\n    addresses: [8.8.8.8]\n  - name: Fast\n    addresses: [1.1.1.1]\n    qps: 0\n",
+        )
+        .unwrap();
+        let Err(CustomServersError::InvalidServers(invalid_servers)) =
+            read_custom_servers_list(yaml, IpAddr::V4, Protocol::Udp, &NO_HOSTNAMES)
+        else {
+            panic!("expected invalid servers");
+        };
+        assert_eq!(
+            invalid_servers,
+            [InvalidServer {
+                index: 2,
+                line: None,
+                name: String::from("Fast"),
+                message: String::from("qps must be between 1 and 9999"),
+            }]
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_servers_file_error_location() {
+        let content = "[[servers]]\nname = \"Google\"\naddresses = [\"8.8.8.300\"]\n";
+        let Err(CustomServersError::Parse { line, column, .. }) =
+            parse_servers_file(content, CustomServersFormat::Toml)
+        else {
+            panic!("expected a parse error");
+        };
+        assert_eq!((line, column), (3, 14));
+
+        let content = "{\n  \"servers\": [\n    {\"name\": \"Google\", \"prot\": \"udp\"}\n  ]\n}";
+        let Err(CustomServersError::Parse {
+            message,
+            line,
+            column,
+        }) = parse_servers_file(content, CustomServersFormat::Json)
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(message.starts_with("unknown field `prot`"), "{message}");
+        assert_eq!(line, 3);
+        assert!(column > 0);
+
+        let content = "servers:\n  - name: Quad9\n    doh_url: http://dns.quad9.net/dns-query\n";
+        let Err(CustomServersError::Parse { message, line, .. }) =
+            parse_servers_file(content, CustomServersFormat::Yaml)
+        else {
+            panic!("expected a parse error");
+        };
+        assert!(
+            message.contains("DoH URL must start with https://"),
+            "{message}"
+        );
+        // Conversion errors point at the start of the server mapping.
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn test_doh_url() {
+        let url = DohUrl::try_from(String::from("https://[2620:fe::fe]:8443/dns-query")).unwrap();
+        assert_eq!(url.host(), "2620:fe::fe");
+        assert_eq!(url.port(), 8443);
+
+        let url = DohUrl::try_from(String::from("https://dns.google/dns-query")).unwrap();
+        assert_eq!(url.host(), "dns.google");
+        assert_eq!(url.port(), 443);
+
+        assert!(DohUrl::try_from(String::from("https://dns.google/resolve")).is_err());
+    }
+
+    #[test]
+    fn test_custom_servers_format_from_path() {
+        let format = |path: &str| CustomServersFormat::from_path(Path::new(path));
+
+        assert_eq!(format("servers.toml"), CustomServersFormat::Toml);
+        assert_eq!(format("servers.JSON"), CustomServersFormat::Json);
+        assert_eq!(format("servers.yml"), CustomServersFormat::Yaml);
+        assert_eq!(format("servers.yaml"), CustomServersFormat::Yaml);
+        assert_eq!(format("servers.txt"), CustomServersFormat::Plain);
+        assert_eq!(format("servers"), CustomServersFormat::Plain);
+    }
 }
@@ -0,0 +1,414 @@
+use crate::args::ForwarderTarget;
+use crate::args::Protocol;
+use crate::servers::DnsEntry;
+
+use std::fmt::Write;
+use std::net::SocketAddr;
+
+const GENERATED_HEADER: &str = "Generated by dns-bench";
+const DEFAULT_PORT: u16 = 53;
+
+/// `1.1.1.1` or `1.1.1.1#5353` as used by dnsmasq and Pi-hole.
+fn hash_port_addr(socket_addr: &SocketAddr) -> String {
+    if socket_addr.port() == DEFAULT_PORT {
+        socket_addr.ip().to_string()
+    } else {
+        format!("{}#{}", socket_addr.ip(), socket_addr.port())
+    }
+}
+
+fn render_dnsmasq(entries: &[DnsEntry], protocol: Protocol) -> String {
+    let mut out = String::new();
+    if protocol == Protocol::Tcp {
+        out.push_str(
+            "# dnsmasq forwards over UDP and only falls back to TCP for truncated responses.\n",
+        );
+    }
+    out.push_str("no-resolv\n");
+    for entry in entries {
+        let _ = writeln!(out, "# {}", entry.name);
+        let _ = writeln!(out, "server={}", hash_port_addr(&entry.socket_addr));
+    }
+    out
+}
+
+fn render_unbound(entries: &[DnsEntry], protocol: Protocol) -> String {
+    let mut out = String::from("forward-zone:\n    name: \".\"\n");
+    match protocol {
+        Protocol::Tcp => out.push_str("    forward-tcp-upstream: yes\n"),
+        Protocol::Tls => out.push_str("    forward-tls-upstream: yes\n"),
+        Protocol::Udp | Protocol::Https => {}
+    }
+    for entry in entries {
+        let _ = writeln!(out, "    # {}", entry.name);
+        let tls_dns_name = match (protocol, &entry.tls_dns_name) {
+            (Protocol::Tls, Some(tls_dns_name)) => format!("#{tls_dns_name}"),
+            _ => String::new(),
+        };
+        let _ = writeln!(
+            out,
+            "    forward-addr: {}@{}{tls_dns_name}",
+            entry.socket_addr.ip(),
+            entry.socket_addr.port()
+        );
+    }
+    out
+}
+
+fn render_coredns(entries: &[DnsEntry], protocol: Protocol) -> String {
+    let mut out = String::from(". {\n");
+    for entry in entries {
+        let _ = writeln!(out, "    # {}: {}", entry.name, entry.socket_addr);
+    }
+    let scheme = if protocol == Protocol::Tls {
+        "tls://"
+    } else {
+        ""
+    };
+    let addrs = entries
+        .iter()
+        .map(|entry| format!("{scheme}{}", entry.socket_addr))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // CoreDNS accepts a single server name per forward block, all TLS upstreams share it.
+    let tls_dns_name = entries
+        .first()
+        .and_then(|entry| entry.tls_dns_name.as_ref());
+    match (protocol, tls_dns_name) {
+        (Protocol::Tcp, _) => {
+            let _ = writeln!(out, "    forward . {addrs} {{\n        force_tcp\n    }}");
+        }
+        (Protocol::Tls, Some(tls_dns_name)) => {
+            let _ = writeln!(
+                out,
+                "    forward . {addrs} {{\n        tls_servername {tls_dns_name}\n    }}"
+            );
+        }
+        _ => {
+            let _ = writeln!(out, "    forward . {addrs}");
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_pihole(entries: &[DnsEntry], protocol: Protocol) -> String {
+    let mut out = String::new();
+    if protocol == Protocol::Tcp {
+        out.push_str(
+            "# Pi-hole forwards over UDP and only falls back to TCP for truncated responses.\n",
+        );
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let _ = writeln!(out, "# {}", entry.name);
+        let _ = writeln!(
+            out,
+            "PIHOLE_DNS_{}={}",
+            i + 1,
+            hash_port_addr(&entry.socket_addr)
+        );
+    }
+    out
+}
+
+fn is_protocol_supported(target: ForwarderTarget, protocol: Protocol) -> bool {
+    match protocol {
+        Protocol::Udp | Protocol::Tcp => true,
+        Protocol::Tls => matches!(target, ForwarderTarget::Unbound | ForwarderTarget::Coredns),
+        Protocol::Https => false,
+    }
+}
+
+/// Whether upstreams of the two protocols can share one forwarder configuration.
+fn is_protocol_compatible(protocol: Protocol, zone_protocol: Protocol) -> bool {
+    match zone_protocol {
+        Protocol::Udp | Protocol::Tcp => matches!(protocol, Protocol::Udp | Protocol::Tcp),
+        zone_protocol => protocol == zone_protocol,
+    }
+}
+
+/// Keep the TLS upstreams whose certificate the forwarder can verify and note the others.
+///
+/// Upstreams need a TLS server name, and CoreDNS verifies all upstreams of its single
+/// forward block against the server name of the first one.
+fn tls_upstreams(
+    target: ForwarderTarget,
+    entries: Vec<DnsEntry>,
+    out: &mut String,
+) -> Vec<DnsEntry> {
+    let mut zone_tls_dns_name = None;
+    let mut upstreams = Vec::new();
+    for entry in entries {
+        let Some(tls_dns_name) = entry.tls_dns_name.clone() else {
+            let _ = writeln!(
+                out,
+                "# {} ({}) has no TLS server name to verify its certificate against, it is left out.",
+                entry.name,
+                entry.socket_addr.ip()
+            );
+            continue;
+        };
+        if target == ForwarderTarget::Coredns {
+            let zone_tls_dns_name = zone_tls_dns_name.get_or_insert_with(|| tls_dns_name.clone());
+            if *zone_tls_dns_name != tls_dns_name {
+                let _ = writeln!(
+                    out,
+                    "# {} ({}) uses the TLS server name {tls_dns_name}, which cannot be mixed with {zone_tls_dns_name} upstreams, it is left out.",
+                    entry.name,
+                    entry.socket_addr.ip()
+                );
+                continue;
+            }
+        }
+        upstreams.push(entry);
+    }
+    upstreams
+}
+
+/// Render forwarder configuration which sends all queries to the given upstream servers,
+/// using the transport each server was benchmarked with where the forwarder supports it.
+///
+/// The transport of the forwarder is the one of the fastest server. Servers of other
+/// encrypted protocols fall back to plain DNS on port 53 when the forwarder uses plain DNS,
+/// and are left out otherwise. Both are noted per server.
+pub fn render_forwarder_config(
+    target: ForwarderTarget,
+    entries: &[DnsEntry],
+    protocol: Protocol,
+) -> String {
+    let mut out = format!("# {GENERATED_HEADER}\n");
+    let zone_protocol = entries
+        .first()
+        .map(|entry| entry.protocol.unwrap_or(protocol))
+        .filter(|zone_protocol| is_protocol_supported(target, *zone_protocol))
+        .unwrap_or(Protocol::Udp);
+
+    let mut forwarded = Vec::new();
+    for entry in entries {
+        let entry_protocol = entry.protocol.unwrap_or(protocol);
+        if is_protocol_compatible(entry_protocol, zone_protocol) {
+            forwarded.push(entry.clone());
+        } else if is_protocol_compatible(Protocol::Udp, zone_protocol) {
+            let _ = writeln!(
+                out,
+                "# {} ({}) was benchmarked over {entry_protocol}, which is not forwarded to here, plain DNS on port {DEFAULT_PORT} is used instead.",
+                entry.name,
+                entry.socket_addr.ip()
+            );
+            let mut entry = entry.clone();
+            entry.socket_addr.set_port(DEFAULT_PORT);
+            entry.protocol = Some(Protocol::Udp);
+            forwarded.push(entry);
+        } else {
+            let _ = writeln!(
+                out,
+                "# {} ({}) was benchmarked over {entry_protocol}, which cannot be mixed with {zone_protocol} upstreams, it is left out.",
+                entry.name,
+                entry.socket_addr.ip()
+            );
+        }
+    }
+    if zone_protocol == Protocol::Tls {
+        forwarded = tls_upstreams(target, forwarded, &mut out);
+    }
+    let (entries, protocol) = (forwarded, zone_protocol);
+
+    out.push_str(&match target {
+        ForwarderTarget::Dnsmasq => render_dnsmasq(&entries, protocol),
+        ForwarderTarget::Unbound => render_unbound(&entries, protocol),
+        ForwarderTarget::Coredns => render_coredns(&entries, protocol),
+        ForwarderTarget::Pihole => render_pihole(&entries, protocol),
+    });
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<DnsEntry> {
+        vec![
+            DnsEntry::new(String::from("Cloudflare"), "1.1.1.1:53".parse().unwrap()),
+            DnsEntry::new(
+                String::from("Internal"),
+                "[2001:db8::53]:5353".parse().unwrap(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_render_dnsmasq() {
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Dnsmasq, &entries(), Protocol::Udp),
+            "# Generated by dns-bench\n\
+            no-resolv\n\
+            # Cloudflare\n\
+            server=1.1.1.1\n\
+            # Internal\n\
+            server=2001:db8::53#5353\n"
+        );
+    }
+
+    #[test]
+    fn test_render_unbound() {
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Unbound, &entries(), Protocol::Tcp),
+            "# Generated by dns-bench\n\
+            forward-zone:\n    \
+                name: \".\"\n    \
+                forward-tcp-upstream: yes\n    \
+                # Cloudflare\n    \
+                forward-addr: 1.1.1.1@53\n    \
+                # Internal\n    \
+                forward-addr: 2001:db8::53@5353\n"
+        );
+    }
+
+    #[test]
+    fn test_render_coredns() {
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Coredns, &entries(), Protocol::Udp),
+            "# Generated by dns-bench\n\
+            . {\n    \
+                # Cloudflare: 1.1.1.1:53\n    \
+                # Internal: [2001:db8::53]:5353\n    \
+                forward . 1.1.1.1:53 [2001:db8::53]:5353\n\
+            }\n"
+        );
+        assert!(
+            render_forwarder_config(ForwarderTarget::Coredns, &entries(), Protocol::Tcp).contains(
+                "    forward . 1.1.1.1:53 [2001:db8::53]:5353 {\n        force_tcp\n    }\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_tls() {
+        let entries = [DnsEntry {
+            tls_dns_name: Some(String::from("cloudflare-dns.com")),
+            ..DnsEntry::new(String::from("Cloudflare"), "1.1.1.1:853".parse().unwrap())
+        }];
+
+        let unbound = render_forwarder_config(ForwarderTarget::Unbound, &entries, Protocol::Tls);
+        assert!(unbound.contains("    forward-tls-upstream: yes\n"));
+        assert!(unbound.contains("    forward-addr: 1.1.1.1@853#cloudflare-dns.com\n"));
+
+        let coredns = render_forwarder_config(ForwarderTarget::Coredns, &entries, Protocol::Tls);
+        assert!(coredns.contains(
+            "    forward . tls://1.1.1.1:853 {\n        tls_servername cloudflare-dns.com\n    }\n"
+        ));
+
+        let dnsmasq = render_forwarder_config(ForwarderTarget::Dnsmasq, &entries, Protocol::Tls);
+        assert!(dnsmasq.contains("# Cloudflare (1.1.1.1) was benchmarked over tls, which is not forwarded to here, plain DNS on port 53 is used instead.\n"));
+        assert!(dnsmasq.contains("server=1.1.1.1\n"));
+    }
+
+    #[test]
+    fn test_render_tls_providers() {
+        let entries = [
+            DnsEntry {
+                tls_dns_name: Some(String::from("cloudflare-dns.com")),
+                ..DnsEntry::new(String::from("Cloudflare"), "1.1.1.1:853".parse().unwrap())
+            },
+            DnsEntry {
+                tls_dns_name: Some(String::from("dns.quad9.net")),
+                ..DnsEntry::new(String::from("Quad9"), "9.9.9.9:853".parse().unwrap())
+            },
+            DnsEntry::new(String::from("Unnamed"), "192.0.2.53:853".parse().unwrap()),
+            DnsEntry {
+                tls_dns_name: Some(String::from("cloudflare-dns.com")),
+                ..DnsEntry::new(
+                    String::from("Cloudflare Secondary"),
+                    "1.0.0.1:853".parse().unwrap(),
+                )
+            },
+        ];
+
+        // Every upstream is verified against its own server name.
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Unbound, &entries, Protocol::Tls),
+            "# Generated by dns-bench\n\
+            # Unnamed (192.0.2.53) has no TLS server name to verify its certificate against, it is left out.\n\
+            forward-zone:\n    \
+                name: \".\"\n    \
+                forward-tls-upstream: yes\n    \
+                # Cloudflare\n    \
+                forward-addr: 1.1.1.1@853#cloudflare-dns.com\n    \
+                # Quad9\n    \
+                forward-addr: 9.9.9.9@853#dns.quad9.net\n    \
+                # Cloudflare Secondary\n    \
+                forward-addr: 1.0.0.1@853#cloudflare-dns.com\n"
+        );
+
+        // The forward block has a single server name, other providers would fail verification.
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Coredns, &entries, Protocol::Tls),
+            "# Generated by dns-bench\n\
+            # Quad9 (9.9.9.9) uses the TLS server name dns.quad9.net, which cannot be mixed with cloudflare-dns.com upstreams, it is left out.\n\
+            # Unnamed (192.0.2.53) has no TLS server name to verify its certificate against, it is left out.\n\
+            . {\n    \
+                # Cloudflare: 1.1.1.1:853\n    \
+                # Cloudflare Secondary: 1.0.0.1:853\n    \
+                forward . tls://1.1.1.1:853 tls://1.0.0.1:853 {\n        \
+                    tls_servername cloudflare-dns.com\n    \
+                }\n\
+            }\n"
+        );
+    }
+
+    #[test]
+    fn test_render_mixed_protocols() {
+        let dot = DnsEntry {
+            protocol: Some(Protocol::Tls),
+            tls_dns_name: Some(String::from("cloudflare-dns.com")),
+            ..DnsEntry::new(
+                String::from("Cloudflare DoT"),
+                "1.1.1.1:853".parse().unwrap(),
+            )
+        };
+        let plain = DnsEntry {
+            protocol: Some(Protocol::Udp),
+            ..DnsEntry::new(String::from("Google"), "8.8.8.8:53".parse().unwrap())
+        };
+
+        // A UDP run with a DoT server from a custom file never sends plain DNS to port 853.
+        assert_eq!(
+            render_forwarder_config(
+                ForwarderTarget::Dnsmasq,
+                &[plain.clone(), dot.clone()],
+                Protocol::Udp
+            ),
+            "# Generated by dns-bench\n\
+            # Cloudflare DoT (1.1.1.1) was benchmarked over tls, which is not forwarded to here, plain DNS on port 53 is used instead.\n\
+            no-resolv\n\
+            # Google\n\
+            server=8.8.8.8\n\
+            # Cloudflare DoT\n\
+            server=1.1.1.1\n"
+        );
+
+        // Plain servers are not forwarded to over TLS.
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Unbound, &[dot, plain], Protocol::Tls),
+            "# Generated by dns-bench\n\
+            # Google (8.8.8.8) was benchmarked over udp, which cannot be mixed with tls upstreams, it is left out.\n\
+            forward-zone:\n    \
+                name: \".\"\n    \
+                forward-tls-upstream: yes\n    \
+                # Cloudflare DoT\n    \
+                forward-addr: 1.1.1.1@853#cloudflare-dns.com\n"
+        );
+    }
+
+    #[test]
+    fn test_render_pihole() {
+        assert_eq!(
+            render_forwarder_config(ForwarderTarget::Pihole, &entries(), Protocol::Udp),
+            "# Generated by dns-bench\n\
+            # Cloudflare\n\
+            PIHOLE_DNS_1=1.1.1.1\n\
+            # Internal\n\
+            PIHOLE_DNS_2=2001:db8::53#5353\n"
+        );
+    }
+}
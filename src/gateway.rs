@@ -1,24 +1,167 @@
+use crate::args::IpAddr as ArgIpAddr;
+use crate::resolver::BindTarget;
+use crate::system::scoped_socket_addr;
+
+use std::fmt;
 #[cfg(target_os = "linux")]
 use std::fs;
 use std::io;
 use std::net::IpAddr;
 #[cfg(any(test, target_os = "linux"))]
 use std::net::Ipv4Addr;
+#[cfg(any(test, target_os = "linux"))]
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
 use std::process::Command;
 use std::str::FromStr;
 
+/// A default gateway of the system.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Gateway {
+    pub ip: IpAddr,
+    /// The network interface of the route, which scopes link-local IPv6 gateways.
+    pub interface: Option<String>,
+    /// The metric of the route, the OS uses the default route with the lowest one.
+    pub metric: Option<u32>,
+}
+
+impl Gateway {
+    pub fn new(ip: IpAddr) -> Self {
+        Self {
+            ip,
+            interface: None,
+            metric: None,
+        }
+    }
+
+    /// The address to query the router DNS at.
+    pub fn socket_addr(&self, port: u16) -> SocketAddr {
+        // Link-local addresses are only reachable through the interface of the route.
+        let zone = if self.is_link_local() {
+            self.interface.as_deref()
+        } else {
+            None
+        };
+        scoped_socket_addr(self.ip, port, zone)
+    }
+
+    /// Whether this is a link-local IPv6 gateway, which is scoped to its interface.
+    fn is_link_local(&self) -> bool {
+        matches!(self.ip, IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80)
+    }
+
+    /// The source of the queries, so they leave through the interface of the route.
+    ///
+    /// Link-local gateways are already scoped to it. Windows names the interface by its address.
+    pub fn bind_target(&self) -> Option<BindTarget> {
+        if self.is_link_local() {
+            return None;
+        }
+        self.interface
+            .as_ref()
+            .map(|interface| match interface.parse::<IpAddr>() {
+                Ok(addr) => BindTarget::Addr(addr),
+                Err(_) => BindTarget::Interface(interface.clone()),
+            })
+    }
+
+    /// The name of the router DNS in the results, marking the gateway the OS uses.
+    pub fn name(&self, in_use: bool) -> String {
+        let in_use = if in_use { ", in use" } else { "" };
+        match &self.interface {
+            Some(interface) => format!("Router (Gateway) DNS ({interface}{in_use})"),
+            None if in_use.is_empty() => String::from("Router (Gateway) DNS"),
+            None => String::from("Router (Gateway) DNS (in use)"),
+        }
+    }
+}
+
+impl fmt::Display for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ip)?;
+        match (&self.interface, self.metric) {
+            (Some(interface), Some(metric)) => write!(f, " ({interface}, metric {metric})"),
+            (Some(interface), None) => write!(f, " ({interface})"),
+            (None, Some(metric)) => write!(f, " (metric {metric})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Why the DNS of a default gateway is not benchmarked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GatewaySkipReason {
+    /// No default gateway of the name servers IP version was found.
+    NotDetected,
+    /// The gateway address is already benchmarked as another server.
+    AlreadyPresent,
+    /// The gateway did not answer the probe query.
+    NotResponsive,
+}
+
+impl fmt::Display for GatewaySkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GatewaySkipReason::NotDetected => write!(f, "not_detected"),
+            GatewaySkipReason::AlreadyPresent => write!(f, "already_present"),
+            GatewaySkipReason::NotResponsive => write!(f, "not_responsive"),
+        }
+    }
+}
+
+/// A gateway whose DNS is not benchmarked, reported in the structured output.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SkippedGateway {
+    /// The address of the gateway, `None` when no gateway was detected.
+    pub ip: Option<IpAddr>,
+    pub interface: Option<String>,
+    pub reason: GatewaySkipReason,
+    /// The error of the detection or the probe query.
+    pub message: Option<String>,
+}
+
+impl SkippedGateway {
+    pub fn new(gateway: &Gateway, reason: GatewaySkipReason, message: Option<String>) -> Self {
+        Self {
+            ip: Some(gateway.ip),
+            interface: gateway.interface.clone(),
+            reason,
+            message,
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
-fn get_gateway_addr_linux() -> io::Result<IpAddr> {
+fn get_gateways_linux() -> io::Result<Vec<Gateway>> {
     // Primary method: read from /proc/net/route
     if let Ok(s) = fs::read_to_string("/proc/net/route") {
-        if let Ok(gw) = parse_proc_net_route_content(&s) {
-            return Ok(gw);
+        if let Ok(gateways) = parse_proc_net_route_content(&s) {
+            return Ok(gateways);
         }
     }
 
     // Fallback method: use `ip route show default`
+    ip_route_show_default("-4")
+}
+
+#[cfg(target_os = "linux")]
+fn get_ipv6_gateways_linux() -> io::Result<Vec<Gateway>> {
+    // Primary method: read from /proc/net/ipv6_route
+    if let Ok(s) = fs::read_to_string("/proc/net/ipv6_route") {
+        if let Ok(gateways) = parse_proc_net_ipv6_route_content(&s) {
+            return Ok(gateways);
+        }
+    }
+
+    // Fallback method: use `ip -6 route show default`
+    ip_route_show_default("-6")
+}
+
+#[cfg(target_os = "linux")]
+fn ip_route_show_default(family: &str) -> io::Result<Vec<Gateway>> {
     match Command::new("ip")
-        .args(["route", "show", "default"])
+        .args([family, "route", "show", "default"])
         .output()
     {
         Ok(out) if out.status.success() => {
@@ -29,14 +172,24 @@ fn get_gateway_addr_linux() -> io::Result<IpAddr> {
     }
 }
 
+/// Fail with `NotFound` when no default gateway was found.
+fn non_empty(gateways: Vec<Gateway>) -> io::Result<Vec<Gateway>> {
+    if gateways.is_empty() {
+        Err(io::Error::from(io::ErrorKind::NotFound))
+    } else {
+        Ok(gateways)
+    }
+}
+
 #[cfg(any(test, target_os = "linux"))]
-fn parse_proc_net_route_content(content: &str) -> io::Result<IpAddr> {
+fn parse_proc_net_route_content(content: &str) -> io::Result<Vec<Gateway>> {
+    let mut gateways = Vec::new();
     for (i, line) in content.lines().enumerate() {
         if i == 0 {
             continue;
         }
         let cols: Vec<&str> = line.split_whitespace().collect();
-        if cols.len() < 3 {
+        if cols.len() < 7 {
             continue;
         }
         let destination = cols[1];
@@ -55,105 +208,166 @@ fn parse_proc_net_route_content(content: &str) -> io::Result<IpAddr> {
             }
 
             let ip = Ipv4Addr::from(u32::from_le_bytes(bytes));
+            // Default routes of point-to-point links have no gateway.
+            if ip.is_unspecified() {
+                continue;
+            }
 
-            return Ok(IpAddr::from(ip));
+            gateways.push(Gateway {
+                interface: Some(cols[0].to_string()),
+                metric: cols[6].parse().ok(),
+                ..Gateway::new(IpAddr::from(ip))
+            });
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    non_empty(gateways)
 }
 
+/// Parse the default routes of /proc/net/ipv6_route, whose columns are the destination,
+/// its prefix length, the source, its prefix length, the next hop, the metric, the reference
+/// and use counters, the flags and the interface.
 #[cfg(any(test, target_os = "linux"))]
-fn parse_ip_route_default_output(text: &str) -> io::Result<IpAddr> {
-    for line in text.lines() {
-        let ws: Vec<&str> = line.split_whitespace().collect();
-        if ws.len() >= 3 && ws[0] == "default" {
-            for (i, tok) in ws.iter().enumerate() {
-                if *tok == "via" && i + 1 < ws.len() {
-                    if let Ok(ip) = IpAddr::from_str(ws[i + 1]) {
-                        return Ok(ip);
-                    }
-                }
+fn parse_proc_net_ipv6_route_content(content: &str) -> io::Result<Vec<Gateway>> {
+    let mut gateways = Vec::new();
+    for line in content.lines() {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 10 {
+            continue;
+        }
+        let (destination, prefix_len, next_hop) = (cols[0], cols[1], cols[4]);
+        if destination.bytes().all(|b| b == b'0') && prefix_len == "00" {
+            // Addresses are written as 32 hex digits in network order.
+            let next_hop = u128::from_str_radix(next_hop, 16)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            // The unreachable default route of the loopback interface has no next hop.
+            if next_hop == 0 {
+                continue;
             }
+
+            gateways.push(Gateway {
+                interface: Some(cols[9].to_string()),
+                metric: u32::from_str_radix(cols[5], 16).ok(),
+                ..Gateway::new(IpAddr::from(Ipv6Addr::from(next_hop)))
+            });
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    non_empty(gateways)
 }
 
-#[cfg(target_os = "macos")]
-fn get_gateway_addr_macos() -> io::Result<IpAddr> {
-    if let Ok(out) = Command::new("route")
-        .arg("-n")
-        .arg("get")
-        .arg("default")
-        .output()
-    {
-        if out.status.success() {
-            let text = String::from_utf8_lossy(&out.stdout);
-            if let Ok(ip) = parse_route_get_default_output(&text) {
-                return Ok(ip);
+#[cfg(any(test, target_os = "linux"))]
+fn parse_ip_route_default_output(text: &str) -> io::Result<Vec<Gateway>> {
+    let mut gateways = Vec::new();
+    for line in text.lines() {
+        let ws: Vec<&str> = line.split_whitespace().collect();
+        if ws.len() >= 3 && ws[0] == "default" {
+            let value_of = |key: &str| {
+                ws.iter()
+                    .position(|tok| *tok == key)
+                    .and_then(|i| ws.get(i + 1))
+            };
+            if let Some(Ok(ip)) = value_of("via").map(|via| IpAddr::from_str(via)) {
+                gateways.push(Gateway {
+                    interface: value_of("dev").map(|dev| dev.to_string()),
+                    // Routes without a metric have metric 0.
+                    metric: Some(value_of("metric").and_then(|m| m.parse().ok()).unwrap_or(0)),
+                    ..Gateway::new(ip)
+                });
             }
         }
     }
 
-    // fallback netstat -rn
+    non_empty(gateways)
+}
+
+#[cfg(target_os = "macos")]
+fn get_gateways_macos() -> io::Result<Vec<Gateway>> {
+    // `route get` only shows the default route in use, so every default route is read from netstat.
     if let Ok(out) = Command::new("netstat").arg("-rn").output() {
         if out.status.success() {
             let text = String::from_utf8_lossy(&out.stdout);
-            if let Ok(ip) = parse_netstat_rn_output(&text) {
-                return Ok(ip);
+            if let Ok(gateways) = parse_netstat_rn_output(&text) {
+                return Ok(gateways);
             }
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    // fallback route -n get default
+    let out = Command::new("route")
+        .arg("-n")
+        .arg("get")
+        .arg("default")
+        .output()?;
+    let text = String::from_utf8_lossy(&out.stdout);
+    parse_route_get_default_output(&text).map(|gateway| vec![gateway])
 }
 
 #[cfg(any(test, target_os = "macos"))]
-fn parse_route_get_default_output(text: &str) -> io::Result<IpAddr> {
+fn parse_route_get_default_output(text: &str) -> io::Result<Gateway> {
+    let mut gateway: Option<Gateway> = None;
     for line in text.lines() {
         let l = line.trim();
-        if l.starts_with("gateway:") {
-            let parts: Vec<&str> = l.split_whitespace().collect();
-            if parts.len() >= 2 {
+        let parts: Vec<&str> = l.split_whitespace().collect();
+        if parts.len() < 2 {
+            continue;
+        }
+        match parts[0] {
+            "gateway:" => {
                 if let Ok(ip) = IpAddr::from_str(parts[1]) {
-                    return Ok(ip);
+                    gateway = Some(Gateway::new(ip));
                 }
             }
+            "interface:" => {
+                if let Some(gateway) = gateway.as_mut() {
+                    gateway.interface = Some(parts[1].to_string());
+                }
+            }
+            _ => {}
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    gateway.ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
 }
 
 #[cfg(any(test, target_os = "macos"))]
-fn parse_netstat_rn_output(text: &str) -> io::Result<IpAddr> {
+fn parse_netstat_rn_output(text: &str) -> io::Result<Vec<Gateway>> {
+    let mut gateways = Vec::new();
+    let mut netif_col = None;
     for line in text.lines() {
         let trimmed = line.trim();
+        // The columns differ between releases, e.g. Destination Gateway Flags Refs Use Netif Expire
+        if trimmed.starts_with("Destination") {
+            netif_col = trimmed.split_whitespace().position(|col| col == "Netif");
+        }
         if trimmed.starts_with("default ") || trimmed.starts_with("default\t") {
-            // columns typically: Destination Gateway Flags Refs Use Mtu Interface
             let cols: Vec<&str> = trimmed.split_whitespace().collect();
             if cols.len() >= 2 {
                 if let Ok(ip) = IpAddr::from_str(cols[1]) {
-                    return Ok(ip);
+                    gateways.push(Gateway {
+                        interface: netif_col
+                            .and_then(|col| cols.get(col))
+                            .map(|netif| netif.to_string()),
+                        ..Gateway::new(ip)
+                    });
                 }
             }
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    non_empty(gateways)
 }
 
 #[cfg(target_os = "windows")]
-fn get_gateway_addr_windows() -> io::Result<IpAddr> {
+fn get_gateways_windows() -> io::Result<Vec<Gateway>> {
     let output = Command::new("route").arg("PRINT").output()?;
     let text = String::from_utf8_lossy(&output.stdout);
     parse_route_print_output(&text)
 }
 
 #[cfg(any(test, target_os = "windows"))]
-fn parse_route_print_output(text: &str) -> io::Result<IpAddr> {
+fn parse_route_print_output(text: &str) -> io::Result<Vec<Gateway>> {
+    let mut gateways: Vec<Gateway> = Vec::new();
     let mut in_ipv4_section = false;
     for line in text.lines() {
         let l = line.trim();
@@ -172,23 +386,52 @@ fn parse_route_print_output(text: &str) -> io::Result<IpAddr> {
         // Now parse lines with 4 or more columns; find those starting with 0.0.0.0 and second col 0.0.0.0
         let cols: Vec<&str> = l.split_whitespace().collect();
         if cols.len() >= 3 && cols[0] == "0.0.0.0" && cols[1] == "0.0.0.0" {
-            // Gateway is usually cols[2]
+            // Gateway is usually cols[2], followed by the address of the interface and the metric
             if let Ok(ip) = IpAddr::from_str(cols[2]) {
-                return Ok(ip);
+                // Persistent routes repeat active ones.
+                if gateways.iter().any(|gateway| gateway.ip == ip) {
+                    continue;
+                }
+                gateways.push(Gateway {
+                    interface: cols
+                        .get(3)
+                        .filter(|_| cols.len() >= 5)
+                        .map(|c| c.to_string()),
+                    metric: cols.get(4).and_then(|metric| metric.parse().ok()),
+                    ..Gateway::new(ip)
+                });
             }
         }
     }
 
-    Err(io::Error::from(io::ErrorKind::NotFound))
+    non_empty(gateways)
+}
+
+/// The default gateways of the given IP version, the one the OS uses first.
+pub fn get_gateways(ip: ArgIpAddr) -> io::Result<Vec<Gateway>> {
+    let mut gateways = get_platform_gateways(ip)?;
+    // Routes without a metric keep their order after the others.
+    gateways.sort_by_key(|gateway| gateway.metric.unwrap_or(u32::MAX));
+    Ok(gateways)
 }
 
-pub fn get_gateway_addr() -> io::Result<IpAddr> {
+fn get_platform_gateways(ip: ArgIpAddr) -> io::Result<Vec<Gateway>> {
+    if ip == ArgIpAddr::V6 {
+        #[cfg(target_os = "linux")]
+        return get_ipv6_gateways_linux();
+        #[allow(unreachable_code)]
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "IPv6 gateway detection is only supported on Linux",
+        ));
+    }
+
     #[cfg(target_os = "linux")]
-    return get_gateway_addr_linux();
+    return get_gateways_linux();
     #[cfg(target_os = "macos")]
-    return get_gateway_addr_macos();
+    return get_gateways_macos();
     #[cfg(target_os = "windows")]
-    return get_gateway_addr_windows();
+    return get_gateways_windows();
     #[allow(unreachable_code)]
     Err(io::Error::other("Unsupported platform"))
 }
@@ -200,45 +443,122 @@ mod tests {
     use std::net::IpAddr;
     use std::str::FromStr;
 
+    fn describe(gateways: &[Gateway]) -> Vec<String> {
+        gateways.iter().map(Gateway::to_string).collect()
+    }
+
     #[test]
     fn test_parse_proc_net_route_content() {
         let content = load_test_asset!("/gateway/linux_proc_net_route.txt");
         let result = parse_proc_net_route_content(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(
+            describe(&result),
+            [
+                "192.168.0.1 (enp1s0, metric 100)",
+                "192.168.0.1 (wlp0s20f3, metric 600)"
+            ]
+        );
+        assert_eq!(
+            result[0].name(true),
+            "Router (Gateway) DNS (enp1s0, in use)"
+        );
+        assert_eq!(result[1].name(false), "Router (Gateway) DNS (wlp0s20f3)");
+        assert_eq!(
+            result[1].bind_target(),
+            Some(BindTarget::Interface(String::from("wlp0s20f3")))
+        );
+    }
+
+    #[test]
+    fn test_parse_proc_net_ipv6_route_content() {
+        let content = load_test_asset!("/gateway/linux_proc_net_ipv6_route.txt");
+        let result = parse_proc_net_ipv6_route_content(content).unwrap();
+        assert_eq!(
+            describe(&result),
+            [
+                "fe80::1 (enp1s0, metric 100)",
+                "fe80::1 (wlp0s20f3, metric 600)"
+            ]
+        );
     }
 
     #[test]
     fn test_parse_ip_route_default_output() {
         let content = load_test_asset!("/gateway/linux_ip_route_default.txt");
         let result = parse_ip_route_default_output(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(
+            describe(&result),
+            [
+                "192.168.0.1 (enp1s0, metric 100)",
+                "192.168.0.1 (wlp0s20f3, metric 600)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ip_route_default_output_ipv6() {
+        let content = load_test_asset!("/gateway/linux_ip_6_route_default.txt");
+        let result = parse_ip_route_default_output(content).unwrap();
+        assert_eq!(
+            describe(&result),
+            [
+                "fe80::1 (wlp0s20f3, metric 600)",
+                "fe80::1 (enp1s0, metric 1024)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gateway_socket_addr() {
+        let gateway = Gateway {
+            interface: Some(String::from("7")),
+            ..Gateway::new(IpAddr::from_str("fe80::1").unwrap())
+        };
+        assert_eq!(gateway.socket_addr(53).to_string(), "[fe80::1%7]:53");
+        assert_eq!(gateway.bind_target(), None);
+
+        let gateway = Gateway {
+            interface: Some(String::from("7")),
+            ..Gateway::new(IpAddr::from_str("2001:db8::1").unwrap())
+        };
+        assert_eq!(gateway.socket_addr(53).to_string(), "[2001:db8::1]:53");
     }
 
     #[test]
     fn test_parse_route_get_default_output() {
         let content = load_test_asset!("/gateway/mac_route_get_default.txt");
         let result = parse_route_get_default_output(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(result.to_string(), "192.168.0.1 (en0)");
     }
 
     #[test]
     fn test_parse_netstat_rn_output() {
         let content = load_test_asset!("/gateway/mac_netstat_rn.txt");
         let result = parse_netstat_rn_output(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(describe(&result), ["192.168.0.1 (en0)"]);
     }
 
     #[test]
     fn test_parse_route_print_output() {
         let content = load_test_asset!("/gateway/win_route_print.txt");
         let result = parse_route_print_output(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(
+            describe(&result),
+            ["192.168.0.1 (192.168.1.100, metric 25)"]
+        );
+        assert_eq!(
+            result[0].bind_target(),
+            Some(BindTarget::Addr(IpAddr::from_str("192.168.1.100").unwrap()))
+        );
     }
 
     #[test]
     fn test_parse_route_print_output_ru() {
         let content = load_test_asset!("/gateway/win_route_print_ru.txt");
         let result = parse_route_print_output(content).unwrap();
-        assert_eq!(result, IpAddr::from_str("192.168.0.1").unwrap());
+        assert_eq!(
+            describe(&result),
+            ["192.168.0.1 (192.168.0.198, metric 25)"]
+        );
     }
 }
@@ -1,15 +1,19 @@
 mod app;
+mod apply;
 mod args;
 mod bench;
 mod cli;
 mod commands;
 mod config;
 mod custom;
+mod forwarder;
 mod gateway;
 mod output;
+mod recommend;
 mod resolver;
 mod result;
 mod servers;
+mod stamp;
 mod system;
 #[cfg(test)]
 mod test_utils;
@@ -0,0 +1,268 @@
+use crate::output::display_names;
+use crate::output::OutputFormatter;
+use crate::output::OutputFormatterContext;
+use crate::output::OutputFormatterError;
+use crate::result::RawResultEntry;
+use crate::result::TimeResult;
+use quick_xml::events::BytesText;
+use quick_xml::writer::Writer;
+use std::io;
+use std::time::Duration;
+use tabled::settings as tabled_settings;
+
+const CHART_WIDTH: u32 = 960;
+const LABEL_AREA_WIDTH: u32 = 320;
+const RIGHT_PADDING: u32 = 40;
+const TOP_PADDING: u32 = 50;
+const BOTTOM_PADDING: u32 = 40;
+const ROW_HEIGHT: u32 = 24;
+const BAR_HEIGHT: u32 = 14;
+const AXIS_TICKS: u32 = 5;
+const FONT_FAMILY: &str = "sans-serif";
+
+/// Map the colors used in the results table onto SVG fill colors, so the chart
+/// follows the same latency thresholds.
+fn svg_fill_color(color: &tabled_settings::Color) -> &'static str {
+    if *color == tabled_settings::Color::FG_BRIGHT_GREEN {
+        "#2ecc71"
+    } else if *color == tabled_settings::Color::FG_BRIGHT_YELLOW {
+        "#f1c40f"
+    } else if *color == tabled_settings::Color::FG_BRIGHT_RED {
+        "#e74c3c"
+    } else {
+        "#95a5a6"
+    }
+}
+
+fn duration_to_millis(duration: &Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn succeeded_millis(time: &TimeResult) -> Option<f64> {
+    match time {
+        TimeResult::Succeeded(duration) => Some(duration_to_millis(duration)),
+        TimeResult::Failed(_) => None,
+    }
+}
+
+/// Horizontal bar chart of average latency per server with min–max whiskers.
+#[derive(Debug, Clone)]
+pub struct SvgChartOutputFormatter;
+
+impl SvgChartOutputFormatter {
+    fn write_svg<W: io::Write>(
+        results: &[RawResultEntry],
+        ctx: &OutputFormatterContext,
+        writer: &mut Writer<W>,
+    ) -> io::Result<()> {
+        let system_dns_ips = ctx.system_dns_ips.clone().unwrap_or_default();
+        let names = display_names(results);
+        let plot_width = (CHART_WIDTH - LABEL_AREA_WIDTH - RIGHT_PADDING) as f64;
+        let height = TOP_PADDING + ROW_HEIGHT * results.len() as u32 + BOTTOM_PADDING;
+        let scale_max_millis = results
+            .iter()
+            .filter_map(|entry| succeeded_millis(&entry.max_duration))
+            .fold(0.0_f64, f64::max)
+            .max(1.0);
+        let x_of = |millis: f64| LABEL_AREA_WIDTH as f64 + millis / scale_max_millis * plot_width;
+
+        writer
+            .create_element("svg")
+            .with_attribute(("xmlns", "http://www.w3.org/2000/svg"))
+            .with_attribute(("width", CHART_WIDTH.to_string().as_str()))
+            .with_attribute(("height", height.to_string().as_str()))
+            .with_attribute(("font-family", FONT_FAMILY))
+            .with_attribute(("font-size", "12"))
+            .write_inner_content(|w| {
+                w.create_element("rect")
+                    .with_attribute(("width", "100%"))
+                    .with_attribute(("height", "100%"))
+                    .with_attribute(("fill", "#ffffff"))
+                    .write_empty()?;
+                w.create_element("text")
+                    .with_attribute(("x", LABEL_AREA_WIDTH.to_string().as_str()))
+                    .with_attribute(("y", "24"))
+                    .with_attribute(("font-size", "14"))
+                    .with_attribute(("font-weight", "bold"))
+                    .write_text_content(BytesText::new(&format!(
+                        "Average latency of {} (whiskers: min–max){}",
+                        ctx.config.domain,
+                        if ctx.incomplete { ", incomplete" } else { "" }
+                    )))?;
+
+                // Vertical grid lines with axis labels.
+                let axis_y = TOP_PADDING + ROW_HEIGHT * results.len() as u32;
+                for tick in 0..=AXIS_TICKS {
+                    let millis = scale_max_millis * tick as f64 / AXIS_TICKS as f64;
+                    let x = format!("{:.1}", x_of(millis));
+                    w.create_element("line")
+                        .with_attribute(("x1", x.as_str()))
+                        .with_attribute(("x2", x.as_str()))
+                        .with_attribute(("y1", TOP_PADDING.to_string().as_str()))
+                        .with_attribute(("y2", axis_y.to_string().as_str()))
+                        .with_attribute(("stroke", "#dddddd"))
+                        .write_empty()?;
+                    w.create_element("text")
+                        .with_attribute(("x", x.as_str()))
+                        .with_attribute(("y", (axis_y + 16).to_string().as_str()))
+                        .with_attribute(("text-anchor", "middle"))
+                        .write_text_content(BytesText::new(&format!("{millis:.1} ms")))?;
+                }
+
+                for (i, (entry, name)) in results.iter().zip(&names).enumerate() {
+                    let row_y = TOP_PADDING + ROW_HEIGHT * i as u32;
+                    let center_y = row_y as f64 + ROW_HEIGHT as f64 / 2.0;
+                    let marker = if system_dns_ips.contains(&entry.ip) {
+                        "> "
+                    } else {
+                        ""
+                    };
+
+                    w.create_element("text")
+                        .with_attribute(("x", (LABEL_AREA_WIDTH - 8).to_string().as_str()))
+                        .with_attribute(("y", format!("{:.1}", center_y + 4.0).as_str()))
+                        .with_attribute(("text-anchor", "end"))
+                        .write_text_content(BytesText::new(&format!(
+                            "{marker}{name} ({}){}",
+                            entry.ip,
+                            if entry.incomplete { " *" } else { "" }
+                        )))?;
+
+                    let Some(avg_millis) = succeeded_millis(&entry.avg_duration) else {
+                        w.create_element("text")
+                            .with_attribute(("x", (LABEL_AREA_WIDTH + 4).to_string().as_str()))
+                            .with_attribute(("y", format!("{:.1}", center_y + 4.0).as_str()))
+                            .with_attribute(("fill", svg_fill_color(&entry.avg_duration_color)))
+                            .write_text_content(BytesText::new(&entry.avg_duration.to_string()))?;
+                        continue;
+                    };
+
+                    let bar_y = center_y - BAR_HEIGHT as f64 / 2.0;
+                    w.create_element("rect")
+                        .with_attribute(("x", LABEL_AREA_WIDTH.to_string().as_str()))
+                        .with_attribute(("y", format!("{bar_y:.1}").as_str()))
+                        .with_attribute((
+                            "width",
+                            format!("{:.1}", x_of(avg_millis) - LABEL_AREA_WIDTH as f64).as_str(),
+                        ))
+                        .with_attribute(("height", BAR_HEIGHT.to_string().as_str()))
+                        .with_attribute(("fill", svg_fill_color(&entry.avg_duration_color)))
+                        .write_inner_content(|bar| {
+                            bar.create_element("title")
+                                .write_text_content(BytesText::new(&format!(
+                                    "{name}: avg {}, min {}, max {}",
+                                    entry.avg_duration, entry.min_duration, entry.max_duration
+                                )))?;
+                            Ok(())
+                        })?;
+
+                    if let (Some(min_millis), Some(max_millis)) = (
+                        succeeded_millis(&entry.min_duration),
+                        succeeded_millis(&entry.max_duration),
+                    ) {
+                        let (min_x, max_x) = (x_of(min_millis), x_of(max_millis));
+                        let whisker_top = format!("{:.1}", center_y - BAR_HEIGHT as f64 / 3.0);
+                        let whisker_bottom = format!("{:.1}", center_y + BAR_HEIGHT as f64 / 3.0);
+                        let center_y = format!("{center_y:.1}");
+                        for (x1, x2, y1, y2) in [
+                            (min_x, max_x, center_y.as_str(), center_y.as_str()),
+                            (min_x, min_x, whisker_top.as_str(), whisker_bottom.as_str()),
+                            (max_x, max_x, whisker_top.as_str(), whisker_bottom.as_str()),
+                        ] {
+                            w.create_element("line")
+                                .with_attribute(("x1", format!("{x1:.1}").as_str()))
+                                .with_attribute(("x2", format!("{x2:.1}").as_str()))
+                                .with_attribute(("y1", y1))
+                                .with_attribute(("y2", y2))
+                                .with_attribute(("stroke", "#333333"))
+                                .write_empty()?;
+                        }
+                    }
+                }
+
+                Ok(())
+            })?;
+
+        Ok(())
+    }
+}
+
+impl OutputFormatter for SvgChartOutputFormatter {
+    fn write(
+        &self,
+        results: &[RawResultEntry],
+        ctx: OutputFormatterContext,
+        w: &mut dyn io::Write,
+    ) -> Result<(), OutputFormatterError> {
+        let mut writer = Writer::new_with_indent(w, b' ', 2);
+        Self::write_svg(results, &ctx, &mut writer)
+            .map_err::<OutputFormatterError, _>(From::from)?;
+        writeln!(writer.into_inner()).map_err::<OutputFormatterError, _>(From::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DnsBenchConfig;
+    use crate::result::MeasureResult;
+    use std::net::IpAddr;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_svg_chart() {
+        let results = &[
+            RawResultEntry::from(vec![
+                MeasureResult {
+                    name: String::from("Cloudflare"),
+                    ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
+                    resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+                    time: TimeResult::Succeeded(Duration::from_millis(10)),
+                },
+                MeasureResult {
+                    name: String::from("Cloudflare"),
+                    ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
+                    resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+                    time: TimeResult::Succeeded(Duration::from_millis(50)),
+                },
+            ]),
+            RawResultEntry::from(vec![MeasureResult {
+                name: String::from("Tom & Jerry <DNS>"),
+                ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                bind: None,
+                resolved_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+                time: TimeResult::Failed(String::from("Timeout")),
+            }]),
+        ];
+        let ctx = OutputFormatterContext {
+            config: DnsBenchConfig::default(),
+            system_dns_ips: Some(vec![IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))]),
+            recommendation: None,
+            skipped_gateways: Vec::new(),
+            incomplete: false,
+        };
+
+        let mut buffer = Vec::new();
+        SvgChartOutputFormatter
+            .write(results, ctx, &mut buffer)
+            .unwrap();
+        let svg = String::from_utf8(buffer).unwrap();
+
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("&gt; Cloudflare (1.1.1.1)"));
+        // Average of 30 ms is green with the default thresholds, the bar spans half of the plot.
+        assert!(svg.contains(
+            "<rect x=\"320\" y=\"55.0\" width=\"360.0\" height=\"14\" fill=\"#2ecc71\">"
+        ));
+        // Whisker from min (10 ms) to max (50 ms).
+        assert!(svg.contains("<line x1=\"440.0\" x2=\"920.0\" y1=\"62.0\" y2=\"62.0\""));
+        // Failed entries are listed without a bar and names are escaped.
+        assert!(svg.contains("Tom &amp; Jerry &lt;DNS&gt; (10.0.0.1)"));
+        assert!(svg.contains("fill=\"#95a5a6\">No responses</text>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -11,6 +11,7 @@ use std::string::FromUtf8Error;
 struct CsvResultEntry {
     name: String,
     ip: IpAddr,
+    bind: Option<IpAddr>,
     last_resolved_ip: IpAddr,
     total_requests: i32,
     successful_requests: i32,
@@ -21,6 +22,11 @@ struct CsvResultEntry {
     max_duration_error: Option<String>,
     avg_duration_value_ms: Option<String>,
     avg_duration_error: Option<String>,
+    incomplete: bool,
+    /// Whether the run was interrupted, so servers which were not benchmarked are missing.
+    interrupted: bool,
+    refused_requests: i32,
+    timeout_bursts: i32,
 }
 
 impl From<RawResultEntry> for CsvResultEntry {
@@ -28,6 +34,7 @@ impl From<RawResultEntry> for CsvResultEntry {
         CsvResultEntry {
             name: value.name,
             ip: value.ip,
+            bind: value.bind,
             last_resolved_ip: value.last_resolved_ip,
             total_requests: value.total_requests,
             successful_requests: value.successful_requests,
@@ -38,6 +45,10 @@ impl From<RawResultEntry> for CsvResultEntry {
             max_duration_error: value.max_duration.get_error_str().map(|v| v.to_string()),
             avg_duration_value_ms: value.avg_duration.get_duration_millis(),
             avg_duration_error: value.avg_duration.get_error_str().map(|v| v.to_string()),
+            incomplete: value.incomplete,
+            interrupted: false,
+            refused_requests: value.refused_requests,
+            timeout_bursts: value.timeout_bursts,
         }
     }
 }
@@ -84,11 +95,17 @@ impl OutputFormatter for CsvOutputFormatter {
     fn write(
         &self,
         results: &[RawResultEntry],
-        _ctx: OutputFormatterContext,
+        ctx: OutputFormatterContext,
         w: &mut dyn io::Write,
     ) -> Result<(), OutputFormatterError> {
-        let csv_entries: Vec<CsvResultEntry> =
-            results.iter().cloned().map(CsvResultEntry::from).collect();
+        let csv_entries: Vec<CsvResultEntry> = results
+            .iter()
+            .cloned()
+            .map(|entry| CsvResultEntry {
+                interrupted: ctx.incomplete,
+                ..CsvResultEntry::from(entry)
+            })
+            .collect();
 
         let csv_string = convert_result_entries_to_csv_string(csv_entries)
             .map_err::<OutputFormatterError, _>(From::from)?;
@@ -114,18 +131,21 @@ mod tests {
                 MeasureResult {
                     name: String::from("Google"),
                     ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(144, 144, 144, 144)),
                     time: TimeResult::Succeeded(Duration::new(0, 100)),
                 },
                 MeasureResult {
                     name: String::from("Google"),
                     ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(144, 144, 144, 144)),
                     time: TimeResult::Succeeded(Duration::new(0, 200)),
                 },
                 MeasureResult {
                     name: String::from("Google"),
                     ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(144, 144, 144, 144)),
                     time: TimeResult::Failed(String::from("Timeout")),
                 },
@@ -134,18 +154,21 @@ mod tests {
                 MeasureResult {
                     name: String::from("Cloudflare"),
                     ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
                     time: TimeResult::Succeeded(Duration::new(0, 50)),
                 },
                 MeasureResult {
                     name: String::from("Cloudflare"),
                     ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
                     time: TimeResult::Succeeded(Duration::new(0, 60)),
                 },
                 MeasureResult {
                     name: String::from("Cloudflare"),
                     ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
                     resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
                     time: TimeResult::Succeeded(Duration::new(0, 70)),
                 },
@@ -160,9 +183,40 @@ mod tests {
         )
         .unwrap();
         let expected_csv = "\
-            name,ip,last_resolved_ip,total_requests,successful_requests,successful_requests_percentage,min_duration_value_ms,min_duration_error,max_duration_value_ms,max_duration_error,avg_duration_value_ms,avg_duration_error\n\
-            Google,8.8.8.8,144.144.144.144,3,2,66.66667,0.000100,,0.000200,,0.000150,\n\
-            Cloudflare,1.1.1.1,145.145.145.145,3,3,100.0,0.000050,,0.000070,,0.000060,\n";
+            name,ip,bind,last_resolved_ip,total_requests,successful_requests,successful_requests_percentage,min_duration_value_ms,min_duration_error,max_duration_value_ms,max_duration_error,avg_duration_value_ms,avg_duration_error,incomplete,interrupted,refused_requests,timeout_bursts\n\
+            Google,8.8.8.8,,144.144.144.144,3,2,66.66667,0.000100,,0.000200,,0.000150,,false,false,0,0\n\
+            Cloudflare,1.1.1.1,,145.145.145.145,3,3,100.0,0.000050,,0.000070,,0.000060,,false,false,0,0\n";
         assert_eq!(csv_string, expected_csv);
     }
+
+    #[test]
+    fn test_output_interrupted() {
+        let ctx = OutputFormatterContext {
+            config: crate::config::DnsBenchConfig::default(),
+            system_dns_ips: None,
+            recommendation: None,
+            skipped_gateways: Vec::new(),
+            incomplete: true,
+        };
+        let results = vec![RawResultEntry::from(vec![MeasureResult {
+            name: String::from("Cloudflare"),
+            ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            bind: None,
+            resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+            time: TimeResult::Succeeded(Duration::new(0, 50)),
+        }])];
+
+        let mut buffer = Vec::new();
+        CsvOutputFormatter
+            .write(&results, ctx, &mut buffer)
+            .unwrap();
+        let mut reader = csv::Reader::from_reader(buffer.as_slice());
+        let headers = reader.headers().unwrap().clone();
+        let record = reader.records().next().unwrap().unwrap();
+        let field = |name: &str| &record[headers.iter().position(|h| h == name).unwrap()];
+
+        // All requests to the server were made, but the run as a whole is incomplete.
+        assert_eq!(field("incomplete"), "false");
+        assert_eq!(field("interrupted"), "true");
+    }
 }
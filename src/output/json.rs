@@ -1,6 +1,8 @@
+use crate::gateway::SkippedGateway;
 use crate::output::OutputFormatter;
 use crate::output::OutputFormatterContext;
 use crate::output::OutputFormatterError;
+use crate::recommend::Recommendation;
 use crate::result::RawResultEntry;
 use crate::result::TimeResult;
 use std::io;
@@ -10,6 +12,7 @@ use std::net::IpAddr;
 struct JsonResultEntry {
     name: String,
     ip: IpAddr,
+    bind: Option<IpAddr>,
     last_resolved_ip: IpAddr,
     total_requests: i32,
     successful_requests: i32,
@@ -17,6 +20,11 @@ struct JsonResultEntry {
     min_duration: TimeResult,
     max_duration: TimeResult,
     avg_duration: TimeResult,
+    incomplete: bool,
+    /// Whether the run was interrupted, so servers which were not benchmarked are missing.
+    interrupted: bool,
+    refused_requests: i32,
+    timeout_bursts: i32,
 }
 
 impl From<RawResultEntry> for JsonResultEntry {
@@ -24,6 +32,7 @@ impl From<RawResultEntry> for JsonResultEntry {
         JsonResultEntry {
             name: value.name,
             ip: value.ip,
+            bind: value.bind,
             last_resolved_ip: value.last_resolved_ip,
             total_requests: value.total_requests,
             successful_requests: value.successful_requests,
@@ -31,10 +40,23 @@ impl From<RawResultEntry> for JsonResultEntry {
             min_duration: value.min_duration,
             max_duration: value.max_duration,
             avg_duration: value.avg_duration,
+            incomplete: value.incomplete,
+            interrupted: false,
+            refused_requests: value.refused_requests,
+            timeout_bursts: value.timeout_bursts,
         }
     }
 }
 
+/// The output with `--json-object`, the result entries alone are written as an array otherwise.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct JsonOutput {
+    incomplete: bool,
+    result_entries: Vec<JsonResultEntry>,
+    recommendation: Option<Recommendation>,
+    skipped_gateways: Vec<SkippedGateway>,
+}
+
 #[derive(Debug, Clone)]
 pub struct JsonOutputFormatter;
 
@@ -42,17 +64,121 @@ impl OutputFormatter for JsonOutputFormatter {
     fn write(
         &self,
         results: &[RawResultEntry],
-        _ctx: OutputFormatterContext,
+        ctx: OutputFormatterContext,
         w: &mut dyn io::Write,
     ) -> Result<(), OutputFormatterError> {
-        let json_entries: Vec<JsonResultEntry> =
-            results.iter().cloned().map(JsonResultEntry::from).collect();
+        let result_entries: Vec<JsonResultEntry> = results
+            .iter()
+            .cloned()
+            .map(|entry| JsonResultEntry {
+                interrupted: ctx.incomplete,
+                ..JsonResultEntry::from(entry)
+            })
+            .collect();
 
-        let json_string = serde_json::to_string_pretty(&json_entries)
-            .map_err::<OutputFormatterError, _>(From::from)?;
+        let json_string = if ctx.config.json_object {
+            serde_json::to_string_pretty(&JsonOutput {
+                incomplete: ctx.incomplete,
+                result_entries,
+                recommendation: ctx.recommendation,
+                skipped_gateways: ctx.skipped_gateways,
+            })
+        } else {
+            serde_json::to_string_pretty(&result_entries)
+        }
+        .map_err::<OutputFormatterError, _>(From::from)?;
 
         writeln!(w, "{}", json_string).map_err::<OutputFormatterError, _>(From::from)?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DnsBenchConfig;
+    use crate::gateway::GatewaySkipReason;
+    use crate::recommend::RecommendedServer;
+    use crate::result::MeasureResult;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    fn results() -> Vec<RawResultEntry> {
+        vec![RawResultEntry::from(vec![MeasureResult {
+            name: String::from("Cloudflare"),
+            ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            bind: None,
+            resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+            time: TimeResult::Succeeded(Duration::new(0, 50)),
+        }])]
+    }
+
+    #[test]
+    fn test_output_array() {
+        let ctx = OutputFormatterContext {
+            config: DnsBenchConfig::default(),
+            system_dns_ips: None,
+            recommendation: None,
+            skipped_gateways: Vec::new(),
+            incomplete: false,
+        };
+
+        let mut buffer = Vec::new();
+        JsonOutputFormatter
+            .write(&results(), ctx, &mut buffer)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+
+        assert_eq!(value[0]["name"], "Cloudflare");
+        assert_eq!(value[0]["successful_requests"], 1);
+        assert_eq!(value[0]["interrupted"], false);
+        assert_eq!(value.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_output_object() {
+        let ctx = OutputFormatterContext {
+            config: DnsBenchConfig {
+                json_object: true,
+                ..DnsBenchConfig::default()
+            },
+            system_dns_ips: None,
+            recommendation: Some(Recommendation {
+                primary: RecommendedServer {
+                    name: String::from("Cloudflare"),
+                    ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                    bind: None,
+                    score: 0.5,
+                },
+                secondary: None,
+                system_dns_comparison: None,
+            }),
+            skipped_gateways: vec![SkippedGateway {
+                ip: Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))),
+                interface: Some(String::from("wlan0")),
+                reason: GatewaySkipReason::NotResponsive,
+                message: Some(String::from("request timed out")),
+            }],
+            incomplete: true,
+        };
+
+        let mut buffer = Vec::new();
+        JsonOutputFormatter
+            .write(&results(), ctx, &mut buffer)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&buffer).unwrap();
+
+        assert_eq!(value["incomplete"], true);
+        assert_eq!(value["result_entries"][0]["name"], "Cloudflare");
+        assert_eq!(value["result_entries"][0]["incomplete"], false);
+        assert_eq!(value["result_entries"][0]["interrupted"], true);
+        assert_eq!(value["result_entries"][0]["refused_requests"], 0);
+        assert_eq!(value["result_entries"][0]["successful_requests"], 1);
+        assert_eq!(value["recommendation"]["primary"]["ip"], "1.1.1.1");
+        assert_eq!(value["recommendation"]["primary"]["score"], 0.5);
+        assert!(value["recommendation"]["secondary"].is_null());
+        assert_eq!(value["skipped_gateways"][0]["ip"], "192.168.0.1");
+        assert_eq!(value["skipped_gateways"][0]["reason"], "not_responsive");
+    }
+}
@@ -1,17 +1,22 @@
+mod chart;
 mod csv;
 mod json;
 mod table;
 mod xml;
 
+pub use chart::SvgChartOutputFormatter;
 pub use csv::CsvConversionError;
 pub use csv::CsvOutputFormatter;
 pub use json::JsonOutputFormatter;
+pub use table::render_servers_table;
 pub use table::TableOutputFormatter;
 pub use xml::XmlConversionError;
 pub use xml::XmlOutputFormatter;
 
 use crate::args::Format;
 use crate::config::DnsBenchConfig;
+use crate::gateway::SkippedGateway;
+use crate::recommend::Recommendation;
 use crate::result::RawResultEntry;
 use std::fmt;
 use std::io;
@@ -21,6 +26,11 @@ use std::net::IpAddr;
 pub struct OutputFormatterContext {
     pub config: DnsBenchConfig,
     pub system_dns_ips: Option<Vec<IpAddr>>,
+    pub recommendation: Option<Recommendation>,
+    /// The gateways whose DNS is not benchmarked, with the reason.
+    pub skipped_gateways: Vec<SkippedGateway>,
+    /// Whether the run was interrupted, so not every server was benchmarked.
+    pub incomplete: bool,
 }
 
 #[derive(Debug, derive_more::Error, derive_more::From)]
@@ -51,6 +61,19 @@ pub trait OutputFormatter {
     ) -> Result<(), OutputFormatterError>;
 }
 
+/// The names of the results in the table and chart, followed by the source address of the queries
+/// when the results were measured from more than one, so the same server can be told apart per uplink.
+fn display_names(results: &[RawResultEntry]) -> Vec<String> {
+    let several_binds = results.iter().any(|entry| entry.bind != results[0].bind);
+    results
+        .iter()
+        .map(|entry| match entry.bind {
+            Some(bind) if several_binds => format!("{} via {bind}", entry.name),
+            _ => entry.name.clone(),
+        })
+        .collect()
+}
+
 pub fn get_output_formatter(format: &Format) -> Box<dyn OutputFormatter> {
     match format {
         Format::HumanReadable => Box::new(TableOutputFormatter {}),
@@ -59,3 +82,43 @@ pub fn get_output_formatter(format: &Format) -> Box<dyn OutputFormatter> {
         Format::Csv => Box::new(CsvOutputFormatter {}),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::MeasureResult;
+    use crate::result::TimeResult;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    fn result(name: &str, bind: Option<IpAddr>) -> RawResultEntry {
+        RawResultEntry::from(vec![MeasureResult {
+            name: String::from(name),
+            ip: IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+            bind,
+            resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+            time: TimeResult::Succeeded(Duration::from_millis(20)),
+        }])
+    }
+
+    #[test]
+    fn test_display_names() {
+        let lte = Some(IpAddr::V4(Ipv4Addr::new(10, 64, 0, 2)));
+        let wifi = Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)));
+
+        // A single source is listed in the config summary, not in every name.
+        assert_eq!(
+            display_names(&[result("Quad9", lte), result("Google", lte)]),
+            vec!["Quad9", "Google"]
+        );
+        assert_eq!(
+            display_names(&[
+                result("Quad9", lte),
+                result("Quad9", wifi),
+                result("Google", None)
+            ]),
+            vec!["Quad9 via 10.64.0.2", "Quad9 via 192.168.1.20", "Google"]
+        );
+        assert!(display_names(&[]).is_empty());
+    }
+}
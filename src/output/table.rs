@@ -1,9 +1,15 @@
+use crate::args::Protocol;
 use crate::args::Style;
+use crate::config::DnsBenchConfig;
+use crate::output::display_names;
 use crate::output::OutputFormatter;
 use crate::output::OutputFormatterContext;
 use crate::output::OutputFormatterError;
+use crate::recommend::Recommendation;
+use crate::recommend::RecommendedServer;
 use crate::result::RawResultEntry;
 use crate::result::TimeResult;
+use crate::servers::DnsEntry;
 use std::io;
 use std::net::IpAddr;
 use tabled::settings as tabled_settings;
@@ -18,7 +24,8 @@ struct TabledResultEntry {
     ip: IpAddr,
     #[tabled(rename = "Last resolved IP")]
     last_resolved_ip: IpAddr,
-    /// String with the following format: "successful_requests/total_requests (success_rate)"
+    /// String with the following format: "successful_requests/total_requests (success_rate)",
+    /// followed by an asterisk when the requests to the server were interrupted.
     #[tabled(rename = "Success rate")]
     successful_requests: String,
     #[tabled(skip)]
@@ -44,10 +51,11 @@ impl From<RawResultEntry> for TabledResultEntry {
             ip: value.ip,
             last_resolved_ip: value.last_resolved_ip,
             successful_requests: format!(
-                "{}/{} ({:.2}%)",
+                "{}/{} ({:.2}%){}",
                 value.successful_requests,
                 value.total_requests,
-                value.successful_requests_percentage
+                value.successful_requests_percentage,
+                if value.incomplete { " *" } else { "" }
             ),
             successful_requests_color: value.successful_requests_color,
             min_duration: value.min_duration,
@@ -74,31 +82,20 @@ impl OutputFormatter for TableOutputFormatter {
         let tabled_result_entries = results
             .iter()
             .cloned()
-            .map(|entry| {
+            .zip(display_names(results))
+            .map(|(entry, name)| {
                 let mut tre = TabledResultEntry::from(entry);
-                if system_dns_ips.contains(&tre.ip) {
-                    tre.name = format!("> {}", tre.name);
-                }
+                tre.name = if system_dns_ips.contains(&tre.ip) {
+                    format!("> {name}")
+                } else {
+                    name
+                };
                 tre
             })
             .collect::<Vec<TabledResultEntry>>();
         let mut table = Table::new(tabled_result_entries.clone());
 
-        match ctx.config.style {
-            Style::Empty => table.with(tabled_settings::Style::empty()),
-            Style::Blank => table.with(tabled_settings::Style::blank()),
-            Style::Ascii => table.with(tabled_settings::Style::ascii()),
-            Style::Psql => table.with(tabled_settings::Style::psql()),
-            Style::Markdown => table.with(tabled_settings::Style::markdown()),
-            Style::Modern => table.with(tabled_settings::Style::modern()),
-            Style::Sharp => table.with(tabled_settings::Style::sharp()),
-            Style::Rounded => table.with(tabled_settings::Style::rounded()),
-            Style::ModernRounded => table.with(tabled_settings::Style::modern_rounded()),
-            Style::Extended => table.with(tabled_settings::Style::extended()),
-            Style::Dots => table.with(tabled_settings::Style::dots()),
-            Style::ReStructuredText => table.with(tabled_settings::Style::re_structured_text()),
-            Style::AsciiRounded => table.with(tabled_settings::Style::ascii_rounded()),
-        };
+        apply_style(&mut table, ctx.config.style);
 
         // Center the content of column titles
         table.with(
@@ -127,13 +124,150 @@ impl OutputFormatter for TableOutputFormatter {
 
         writeln!(w, "{}", table).map_err::<OutputFormatterError, _>(From::from)?;
 
+        let rate_limited = results
+            .iter()
+            .filter(|entry| entry.is_rate_limited())
+            .collect::<Vec<_>>();
+        if !rate_limited.is_empty() {
+            write_rate_limit_indicators(&rate_limited, w)
+                .map_err::<OutputFormatterError, _>(From::from)?;
+        }
+
+        if ctx.incomplete {
+            writeln!(
+                w,
+                "The benchmark was interrupted, the results are incomplete."
+            )
+            .map_err::<OutputFormatterError, _>(From::from)?;
+            if results.iter().any(|entry| entry.incomplete) {
+                writeln!(w, "* Not all requests to the server were made.")
+                    .map_err::<OutputFormatterError, _>(From::from)?;
+            }
+        }
+
+        if let Some(recommendation) = &ctx.recommendation {
+            write_recommendation(recommendation, w)
+                .map_err::<OutputFormatterError, _>(From::from)?;
+        }
+
         Ok(())
     }
 }
 
+fn apply_style(table: &mut Table, style: Style) {
+    match style {
+        Style::Empty => table.with(tabled_settings::Style::empty()),
+        Style::Blank => table.with(tabled_settings::Style::blank()),
+        Style::Ascii => table.with(tabled_settings::Style::ascii()),
+        Style::Psql => table.with(tabled_settings::Style::psql()),
+        Style::Markdown => table.with(tabled_settings::Style::markdown()),
+        Style::Modern => table.with(tabled_settings::Style::modern()),
+        Style::Sharp => table.with(tabled_settings::Style::sharp()),
+        Style::Rounded => table.with(tabled_settings::Style::rounded()),
+        Style::ModernRounded => table.with(tabled_settings::Style::modern_rounded()),
+        Style::Extended => table.with(tabled_settings::Style::extended()),
+        Style::Dots => table.with(tabled_settings::Style::dots()),
+        Style::ReStructuredText => table.with(tabled_settings::Style::re_structured_text()),
+        Style::AsciiRounded => table.with(tabled_settings::Style::ascii_rounded()),
+    };
+}
+
+#[derive(Debug, Clone, Tabled)]
+struct TabledServerEntry {
+    #[tabled(rename = "Server name")]
+    name: String,
+    #[tabled(rename = "IP address")]
+    ip: IpAddr,
+    #[tabled(rename = "Port")]
+    port: u16,
+    #[tabled(rename = "Protocol")]
+    protocol: Protocol,
+    #[tabled(rename = "Timeout")]
+    timeout: u64,
+    #[tabled(rename = "Requests")]
+    requests: u16,
+    #[tabled(rename = "Tags")]
+    tags: String,
+}
+
+/// Render the servers of a benchmark as a table, with the configured values where a server does
+/// not override them.
+pub fn render_servers_table(entries: &[DnsEntry], config: &DnsBenchConfig) -> String {
+    let mut table = Table::new(entries.iter().map(|entry| TabledServerEntry {
+        name: entry.name.clone(),
+        ip: entry.socket_addr.ip(),
+        port: entry.socket_addr.port(),
+        protocol: entry.protocol.unwrap_or(config.protocol),
+        timeout: entry.timeout.unwrap_or(config.timeout),
+        requests: entry.requests.unwrap_or(config.requests),
+        tags: entry.tags.join(", "),
+    }));
+    apply_style(&mut table, config.style);
+    table.with(
+        tabled_settings::Modify::new(tabled_settings::object::Rows::first())
+            .with(tabled_settings::Alignment::center()),
+    );
+
+    format!("{table}\n")
+}
+
+fn write_rate_limit_indicators(
+    entries: &[&RawResultEntry],
+    w: &mut dyn io::Write,
+) -> io::Result<()> {
+    writeln!(w, "Possible rate limiting, consider --qps or --interval:")?;
+    for entry in entries {
+        writeln!(
+            w,
+            "  {} ({}): {} REFUSED answers, {} timeout bursts",
+            entry.name, entry.ip, entry.refused_requests, entry.timeout_bursts
+        )?;
+    }
+    Ok(())
+}
+
+fn write_recommendation(recommendation: &Recommendation, w: &mut dyn io::Write) -> io::Result<()> {
+    let describe = |server: &RecommendedServer| match server.bind {
+        Some(bind) => format!("{} ({} via {bind})", server.name, server.ip),
+        None => format!("{} ({})", server.name, server.ip),
+    };
+    writeln!(w, "Recommendation (lower score is better):")?;
+    writeln!(
+        w,
+        "  Primary:   {}, score {:.2}",
+        describe(&recommendation.primary),
+        recommendation.primary.score
+    )?;
+    if let Some(secondary) = &recommendation.secondary {
+        writeln!(
+            w,
+            "  Secondary: {}, score {:.2}",
+            describe(secondary),
+            secondary.score
+        )?;
+    }
+    if let Some(comparison) = &recommendation.system_dns_comparison {
+        writeln!(
+            w,
+            "  Compared to system DNS: {:.1}% {} ({:.2} ms vs {:.2} ms average)",
+            comparison.improvement_percentage.abs(),
+            if comparison.improvement_percentage >= 0.0 {
+                "faster"
+            } else {
+                "slower"
+            },
+            comparison.recommended_avg_duration_ms,
+            comparison.system_avg_duration_ms,
+        )?;
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::result::MeasureResult;
     use std::net::Ipv4Addr;
     use std::time::Duration;
 
@@ -142,6 +276,7 @@ mod tests {
         let raw_result_entry = RawResultEntry {
             name: String::from("Google"),
             ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            bind: None,
             last_resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
             total_requests: 3,
             successful_requests: 2,
@@ -153,6 +288,10 @@ mod tests {
             min_duration_color: tabled_settings::Color::FG_BRIGHT_GREEN,
             max_duration: TimeResult::Succeeded(Duration::new(0, 200)),
             max_duration_color: tabled_settings::Color::FG_BRIGHT_GREEN,
+            durations: vec![Duration::new(0, 100), Duration::new(0, 200)],
+            incomplete: false,
+            refused_requests: 0,
+            timeout_bursts: 0,
         };
 
         let tabled_result_entry = TabledResultEntry::from(raw_result_entry);
@@ -196,4 +335,36 @@ mod tests {
             tabled_settings::Color::FG_BRIGHT_GREEN
         );
     }
+
+    #[test]
+    fn test_interrupted_footnote() {
+        let ctx = OutputFormatterContext {
+            config: DnsBenchConfig::default(),
+            system_dns_ips: None,
+            recommendation: None,
+            skipped_gateways: Vec::new(),
+            incomplete: true,
+        };
+        let mut result = RawResultEntry::from(vec![MeasureResult {
+            name: String::from("Cloudflare"),
+            ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            bind: None,
+            resolved_ip: IpAddr::V4(Ipv4Addr::new(145, 145, 145, 145)),
+            time: TimeResult::Succeeded(Duration::from_millis(20)),
+        }]);
+        let render = |result: &RawResultEntry| {
+            let mut buffer = Vec::new();
+            TableOutputFormatter
+                .write(std::slice::from_ref(result), ctx.clone(), &mut buffer)
+                .unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+
+        let output = render(&result);
+        assert!(output.contains("The benchmark was interrupted"));
+        assert!(!output.contains("* Not all requests"));
+
+        result.incomplete = true;
+        assert!(render(&result).contains("* Not all requests"));
+    }
 }
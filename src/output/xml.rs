@@ -1,6 +1,9 @@
+use crate::gateway::SkippedGateway;
 use crate::output::OutputFormatter;
 use crate::output::OutputFormatterContext;
 use crate::output::OutputFormatterError;
+use crate::recommend::Recommendation;
+use crate::recommend::RecommendedServer;
 use crate::result::RawResultEntry;
 use crate::result::TimeResult;
 use std::fmt;
@@ -12,6 +15,7 @@ use std::string::FromUtf8Error;
 struct XmlResultEntry {
     name: String,
     ip: IpAddr,
+    bind: Option<IpAddr>,
     last_resolved_ip: IpAddr,
     total_requests: i32,
     successful_requests: i32,
@@ -19,6 +23,9 @@ struct XmlResultEntry {
     min_duration: TimeResult,
     max_duration: TimeResult,
     avg_duration: TimeResult,
+    incomplete: bool,
+    refused_requests: i32,
+    timeout_bursts: i32,
 }
 
 impl XmlResultEntry {
@@ -26,61 +33,85 @@ impl XmlResultEntry {
         self,
         writer: &mut quick_xml::writer::Writer<io::Cursor<Vec<u8>>>,
     ) -> io::Result<()> {
-        writer
-            .create_element("ResultEntry")
-            .write_inner_content(|entry_writer| {
-                entry_writer
-                    .create_element("Name")
-                    .write_text_content(quick_xml::events::BytesText::new(&self.name))?;
-                entry_writer.create_element("Ip").write_text_content(
-                    quick_xml::events::BytesText::new(self.ip.to_string().as_str()),
+        let mut element = writer.create_element("ResultEntry");
+        if self.incomplete {
+            element = element.with_attribute(("incomplete", "true"));
+        }
+        element.write_inner_content(|entry_writer| {
+            entry_writer
+                .create_element("Name")
+                .write_text_content(quick_xml::events::BytesText::new(&self.name))?;
+            entry_writer.create_element("Ip").write_text_content(
+                quick_xml::events::BytesText::new(self.ip.to_string().as_str()),
+            )?;
+            if let Some(bind) = self.bind {
+                entry_writer.create_element("Bind").write_text_content(
+                    quick_xml::events::BytesText::new(bind.to_string().as_str()),
                 )?;
+            }
+            entry_writer
+                .create_element("LastResolvedIp")
+                .write_text_content(quick_xml::events::BytesText::new(
+                    self.last_resolved_ip.to_string().as_str(),
+                ))?;
+            entry_writer
+                .create_element("SuccessfulRequests")
+                .write_inner_content(|srwriter| {
+                    srwriter
+                        .create_element("TotalRequests")
+                        .write_text_content(quick_xml::events::BytesText::new(
+                            self.total_requests.to_string().as_str(),
+                        ))?;
+                    srwriter
+                        .create_element("SuccessfulRequests")
+                        .write_text_content(quick_xml::events::BytesText::new(
+                            self.successful_requests.to_string().as_str(),
+                        ))?;
+                    srwriter
+                        .create_element("SuccessfulRequestsPercentage")
+                        .write_text_content(quick_xml::events::BytesText::new(
+                            self.successful_requests_percentage.to_string().as_str(),
+                        ))?;
+                    Ok(())
+                })?;
+            entry_writer
+                .create_element("MinDuration")
+                .with_attribute(("type", self.min_duration.get_xml_type_str()))
+                .write_text_content(quick_xml::events::BytesText::new(
+                    self.min_duration.to_string().as_str(),
+                ))?;
+            entry_writer
+                .create_element("MaxDuration")
+                .with_attribute(("type", self.max_duration.get_xml_type_str()))
+                .write_text_content(quick_xml::events::BytesText::new(
+                    self.max_duration.to_string().as_str(),
+                ))?;
+            entry_writer
+                .create_element("AvgDuration")
+                .with_attribute(("type", self.avg_duration.get_xml_type_str()))
+                .write_text_content(quick_xml::events::BytesText::new(
+                    self.avg_duration.to_string().as_str(),
+                ))?;
+            if self.refused_requests > 0 || self.timeout_bursts > 0 {
                 entry_writer
-                    .create_element("LastResolvedIp")
-                    .write_text_content(quick_xml::events::BytesText::new(
-                        self.last_resolved_ip.to_string().as_str(),
-                    ))?;
-                entry_writer
-                    .create_element("SuccessfulRequests")
-                    .write_inner_content(|srwriter| {
-                        srwriter
-                            .create_element("TotalRequests")
+                    .create_element("RateLimitIndicators")
+                    .write_inner_content(|rlwriter| {
+                        rlwriter
+                            .create_element("RefusedRequests")
                             .write_text_content(quick_xml::events::BytesText::new(
-                                self.total_requests.to_string().as_str(),
+                                self.refused_requests.to_string().as_str(),
                             ))?;
-                        srwriter
-                            .create_element("SuccessfulRequests")
+                        rlwriter
+                            .create_element("TimeoutBursts")
                             .write_text_content(quick_xml::events::BytesText::new(
-                                self.successful_requests.to_string().as_str(),
-                            ))?;
-                        srwriter
-                            .create_element("SuccessfulRequestsPercentage")
-                            .write_text_content(quick_xml::events::BytesText::new(
-                                self.successful_requests_percentage.to_string().as_str(),
+                                self.timeout_bursts.to_string().as_str(),
                             ))?;
                         Ok(())
                     })?;
-                entry_writer
-                    .create_element("MinDuration")
-                    .with_attribute(("type", self.min_duration.get_xml_type_str()))
-                    .write_text_content(quick_xml::events::BytesText::new(
-                        self.min_duration.to_string().as_str(),
-                    ))?;
-                entry_writer
-                    .create_element("MaxDuration")
-                    .with_attribute(("type", self.max_duration.get_xml_type_str()))
-                    .write_text_content(quick_xml::events::BytesText::new(
-                        self.max_duration.to_string().as_str(),
-                    ))?;
-                entry_writer
-                    .create_element("AvgDuration")
-                    .with_attribute(("type", self.avg_duration.get_xml_type_str()))
-                    .write_text_content(quick_xml::events::BytesText::new(
-                        self.avg_duration.to_string().as_str(),
-                    ))?;
+            }
 
-                Ok(())
-            })?;
+            Ok(())
+        })?;
 
         Ok(())
     }
@@ -91,6 +122,7 @@ impl From<RawResultEntry> for XmlResultEntry {
         XmlResultEntry {
             name: value.name,
             ip: value.ip,
+            bind: value.bind,
             last_resolved_ip: value.last_resolved_ip,
             total_requests: value.total_requests,
             successful_requests: value.successful_requests,
@@ -98,10 +130,120 @@ impl From<RawResultEntry> for XmlResultEntry {
             min_duration: value.min_duration,
             max_duration: value.max_duration,
             avg_duration: value.avg_duration,
+            incomplete: value.incomplete,
+            refused_requests: value.refused_requests,
+            timeout_bursts: value.timeout_bursts,
         }
     }
 }
 
+fn write_recommended_server_as_xml(
+    element_name: &str,
+    server: &RecommendedServer,
+    writer: &mut quick_xml::writer::Writer<io::Cursor<Vec<u8>>>,
+) -> io::Result<()> {
+    writer
+        .create_element(element_name)
+        .write_inner_content(|server_writer| {
+            server_writer
+                .create_element("Name")
+                .write_text_content(quick_xml::events::BytesText::new(&server.name))?;
+            server_writer.create_element("Ip").write_text_content(
+                quick_xml::events::BytesText::new(server.ip.to_string().as_str()),
+            )?;
+            if let Some(bind) = server.bind {
+                server_writer.create_element("Bind").write_text_content(
+                    quick_xml::events::BytesText::new(bind.to_string().as_str()),
+                )?;
+            }
+            server_writer.create_element("Score").write_text_content(
+                quick_xml::events::BytesText::new(server.score.to_string().as_str()),
+            )?;
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+fn write_recommendation_as_xml(
+    recommendation: &Recommendation,
+    writer: &mut quick_xml::writer::Writer<io::Cursor<Vec<u8>>>,
+) -> io::Result<()> {
+    writer
+        .create_element("Recommendation")
+        .write_inner_content(|recommendation_writer| {
+            write_recommended_server_as_xml(
+                "Primary",
+                &recommendation.primary,
+                recommendation_writer,
+            )?;
+            if let Some(secondary) = &recommendation.secondary {
+                write_recommended_server_as_xml("Secondary", secondary, recommendation_writer)?;
+            }
+            if let Some(comparison) = &recommendation.system_dns_comparison {
+                recommendation_writer
+                    .create_element("SystemDnsComparison")
+                    .write_inner_content(|cwriter| {
+                        cwriter
+                            .create_element("SystemAvgDurationMs")
+                            .write_text_content(quick_xml::events::BytesText::new(
+                                comparison.system_avg_duration_ms.to_string().as_str(),
+                            ))?;
+                        cwriter
+                            .create_element("RecommendedAvgDurationMs")
+                            .write_text_content(quick_xml::events::BytesText::new(
+                                comparison.recommended_avg_duration_ms.to_string().as_str(),
+                            ))?;
+                        cwriter
+                            .create_element("ImprovementPercentage")
+                            .write_text_content(quick_xml::events::BytesText::new(
+                                comparison.improvement_percentage.to_string().as_str(),
+                            ))?;
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
+fn write_skipped_gateways_as_xml(
+    skipped_gateways: &[SkippedGateway],
+    writer: &mut quick_xml::writer::Writer<io::Cursor<Vec<u8>>>,
+) -> io::Result<()> {
+    writer
+        .create_element("SkippedGateways")
+        .write_inner_content(|gateways_writer| {
+            for gateway in skipped_gateways {
+                gateways_writer
+                    .create_element("SkippedGateway")
+                    .with_attribute(("reason", gateway.reason.to_string().as_str()))
+                    .write_inner_content(|gateway_writer| {
+                        if let Some(ip) = gateway.ip {
+                            gateway_writer.create_element("Ip").write_text_content(
+                                quick_xml::events::BytesText::new(ip.to_string().as_str()),
+                            )?;
+                        }
+                        if let Some(interface) = &gateway.interface {
+                            gateway_writer
+                                .create_element("Interface")
+                                .write_text_content(quick_xml::events::BytesText::new(interface))?;
+                        }
+                        if let Some(message) = &gateway.message {
+                            gateway_writer
+                                .create_element("Message")
+                                .write_text_content(quick_xml::events::BytesText::new(message))?;
+                        }
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+
+    Ok(())
+}
+
 #[derive(Debug, derive_more::Error, derive_more::From)]
 pub enum XmlConversionError {
     Io(io::Error),
@@ -119,15 +261,27 @@ impl fmt::Display for XmlConversionError {
 
 fn convert_result_entries_to_xml_string(
     result_entries: Vec<XmlResultEntry>,
+    recommendation: Option<&Recommendation>,
+    skipped_gateways: &[SkippedGateway],
+    incomplete: bool,
 ) -> Result<String, XmlConversionError> {
     let mut writer = quick_xml::writer::Writer::new(io::Cursor::new(Vec::new()));
 
-    writer
-        .create_element("DnsBenchResultEntries")
+    let mut element = writer.create_element("DnsBenchResultEntries");
+    if incomplete {
+        element = element.with_attribute(("incomplete", "true"));
+    }
+    element
         .write_inner_content(|writer| {
             for entry in result_entries {
                 entry.write_as_xml(writer)?;
             }
+            if let Some(recommendation) = recommendation {
+                write_recommendation_as_xml(recommendation, writer)?;
+            }
+            if !skipped_gateways.is_empty() {
+                write_skipped_gateways_as_xml(skipped_gateways, writer)?;
+            }
             Ok(())
         })
         .map_err(XmlConversionError::Io)?;
@@ -145,14 +299,19 @@ impl OutputFormatter for XmlOutputFormatter {
     fn write(
         &self,
         results: &[RawResultEntry],
-        _ctx: OutputFormatterContext,
+        ctx: OutputFormatterContext,
         w: &mut dyn io::Write,
     ) -> Result<(), OutputFormatterError> {
         let xml_result_entries: Vec<XmlResultEntry> =
             results.iter().cloned().map(XmlResultEntry::from).collect();
 
-        let xml_string = convert_result_entries_to_xml_string(xml_result_entries)
-            .map_err::<OutputFormatterError, _>(From::from)?;
+        let xml_string = convert_result_entries_to_xml_string(
+            xml_result_entries,
+            ctx.recommendation.as_ref(),
+            &ctx.skipped_gateways,
+            ctx.incomplete,
+        )
+        .map_err::<OutputFormatterError, _>(From::from)?;
 
         writeln!(w, "{}", xml_string).map_err::<OutputFormatterError, _>(From::from)?;
 
@@ -163,6 +322,8 @@ impl OutputFormatter for XmlOutputFormatter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::gateway::GatewaySkipReason;
+    use crate::recommend::SystemDnsComparison;
     use std::net::Ipv4Addr;
     use std::time::Duration;
 
@@ -172,6 +333,7 @@ mod tests {
             XmlResultEntry {
                 name: String::from("Google"),
                 ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                bind: None,
                 last_resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
                 total_requests: 3,
                 successful_requests: 2,
@@ -179,10 +341,14 @@ mod tests {
                 min_duration: TimeResult::Succeeded(Duration::new(0, 100)),
                 max_duration: TimeResult::Succeeded(Duration::new(0, 200)),
                 avg_duration: TimeResult::Succeeded(Duration::new(0, 150)),
+                incomplete: false,
+                refused_requests: 0,
+                timeout_bursts: 0,
             },
             XmlResultEntry {
                 name: String::from("Cloudflare"),
                 ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                bind: None,
                 last_resolved_ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
                 total_requests: 3,
                 successful_requests: 3,
@@ -190,9 +356,51 @@ mod tests {
                 min_duration: TimeResult::Succeeded(Duration::new(0, 50)),
                 max_duration: TimeResult::Succeeded(Duration::new(0, 70)),
                 avg_duration: TimeResult::Succeeded(Duration::new(0, 60)),
+                incomplete: false,
+                refused_requests: 0,
+                timeout_bursts: 0,
+            },
+        ];
+        let recommendation = Recommendation {
+            primary: RecommendedServer {
+                name: String::from("Cloudflare"),
+                ip: IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+                bind: None,
+                score: 0.5,
+            },
+            secondary: Some(RecommendedServer {
+                name: String::from("Google"),
+                ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                bind: None,
+                score: 1.25,
+            }),
+            system_dns_comparison: Some(SystemDnsComparison {
+                system_avg_duration_ms: 20.0,
+                recommended_avg_duration_ms: 6.5,
+                improvement_percentage: 67.5,
+            }),
+        };
+        let skipped_gateways = [
+            SkippedGateway {
+                ip: None,
+                interface: None,
+                reason: GatewaySkipReason::NotDetected,
+                message: Some(String::from("entity not found")),
+            },
+            SkippedGateway {
+                ip: Some(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))),
+                interface: Some(String::from("wlan0")),
+                reason: GatewaySkipReason::AlreadyPresent,
+                message: None,
             },
         ];
-        let xml_string = convert_result_entries_to_xml_string(result_entries).unwrap();
+        let xml_string = convert_result_entries_to_xml_string(
+            result_entries,
+            Some(&recommendation),
+            &skipped_gateways,
+            false,
+        )
+        .unwrap();
         let expected_string = "\
             <DnsBenchResultEntries>\
                 <ResultEntry>\
@@ -221,7 +429,64 @@ mod tests {
                     <MaxDuration type=\"succeeded\">70ns</MaxDuration>\
                     <AvgDuration type=\"succeeded\">60ns</AvgDuration>\
                 </ResultEntry>\
+                <Recommendation>\
+                    <Primary>\
+                        <Name>Cloudflare</Name>\
+                        <Ip>1.1.1.1</Ip>\
+                        <Score>0.5</Score>\
+                    </Primary>\
+                    <Secondary>\
+                        <Name>Google</Name>\
+                        <Ip>8.8.8.8</Ip>\
+                        <Score>1.25</Score>\
+                    </Secondary>\
+                    <SystemDnsComparison>\
+                        <SystemAvgDurationMs>20</SystemAvgDurationMs>\
+                        <RecommendedAvgDurationMs>6.5</RecommendedAvgDurationMs>\
+                        <ImprovementPercentage>67.5</ImprovementPercentage>\
+                    </SystemDnsComparison>\
+                </Recommendation>\
+                <SkippedGateways>\
+                    <SkippedGateway reason=\"not_detected\">\
+                        <Message>entity not found</Message>\
+                    </SkippedGateway>\
+                    <SkippedGateway reason=\"already_present\">\
+                        <Ip>192.168.0.1</Ip>\
+                        <Interface>wlan0</Interface>\
+                    </SkippedGateway>\
+                </SkippedGateways>\
             </DnsBenchResultEntries>";
         assert_eq!(xml_string, expected_string);
     }
+
+    #[test]
+    fn test_conversion_incomplete() {
+        let result_entries = vec![XmlResultEntry {
+            name: String::from("Quad9"),
+            ip: IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+            bind: None,
+            last_resolved_ip: IpAddr::V4(Ipv4Addr::new(9, 9, 9, 9)),
+            total_requests: 1,
+            successful_requests: 1,
+            successful_requests_percentage: 100.0,
+            min_duration: TimeResult::Succeeded(Duration::new(0, 80)),
+            max_duration: TimeResult::Succeeded(Duration::new(0, 80)),
+            avg_duration: TimeResult::Succeeded(Duration::new(0, 80)),
+            incomplete: true,
+            refused_requests: 4,
+            timeout_bursts: 0,
+        }];
+        let xml_string =
+            convert_result_entries_to_xml_string(result_entries, None, &[], true).unwrap();
+
+        assert!(xml_string.starts_with(
+            "<DnsBenchResultEntries incomplete=\"true\"><ResultEntry incomplete=\"true\">"
+        ));
+        assert!(xml_string.contains(
+            "<RateLimitIndicators>\
+                <RefusedRequests>4</RefusedRequests>\
+                <TimeoutBursts>0</TimeoutBursts>\
+            </RateLimitIndicators>"
+        ));
+    }
 }
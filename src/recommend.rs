@@ -0,0 +1,266 @@
+use crate::result::RawResultEntry;
+use crate::result::TimeResult;
+use std::cmp::Ordering;
+use std::net::IpAddr;
+use std::time::Duration;
+
+const MEDIAN_WEIGHT: f64 = 0.5;
+const P90_WEIGHT: f64 = 0.3;
+const JITTER_WEIGHT: f64 = 0.2;
+
+fn duration_to_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn round_millis(millis: f64) -> f64 {
+    (millis * 1000.0).round() / 1000.0
+}
+
+/// Composite score of a result entry, lower is better.
+///
+/// The weighted sum of median latency, 90th percentile latency and jitter (in milliseconds)
+/// is divided by the squared success ratio, so unreliable servers are penalized heavily.
+/// Entries without a single successful request have no score.
+pub fn composite_score(entry: &RawResultEntry) -> Option<f64> {
+    let median = entry.percentile(50.0)?;
+    let p90 = entry.percentile(90.0)?;
+    let jitter = entry.jitter().unwrap_or_default();
+    let success_ratio = f64::from(entry.successful_requests_percentage) / 100.0;
+
+    let weighted_latency = MEDIAN_WEIGHT * duration_to_millis(median)
+        + P90_WEIGHT * duration_to_millis(p90)
+        + JITTER_WEIGHT * duration_to_millis(jitter);
+
+    Some(weighted_latency / success_ratio.powi(2))
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecommendedServer {
+    pub name: String,
+    pub ip: IpAddr,
+    /// Source address the queries to the server were sent from, when not left to the OS.
+    pub bind: Option<IpAddr>,
+    pub score: f64,
+}
+
+/// How the recommended pair compares to the currently configured system DNS servers.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SystemDnsComparison {
+    pub system_avg_duration_ms: f64,
+    pub recommended_avg_duration_ms: f64,
+    /// Positive when the recommended pair is faster than the system DNS servers.
+    pub improvement_percentage: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Recommendation {
+    pub primary: RecommendedServer,
+    pub secondary: Option<RecommendedServer>,
+    pub system_dns_comparison: Option<SystemDnsComparison>,
+}
+
+fn avg_duration_millis(entry: &RawResultEntry) -> Option<f64> {
+    match entry.avg_duration {
+        TimeResult::Succeeded(duration) => Some(duration_to_millis(duration)),
+        TimeResult::Failed(_) => None,
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Pick the best primary and secondary resolver by composite score.
+///
+/// The secondary preferably comes from a different provider (server name) than the primary,
+/// so that a single provider outage does not take down both resolvers. Only the entries
+/// `is_candidate` accepts are recommended, and never the system DNS servers, which the pair is
+/// compared to.
+pub fn recommend(
+    results: &[RawResultEntry],
+    system_dns_ips: &[IpAddr],
+    is_candidate: impl Fn(&RawResultEntry) -> bool,
+) -> Option<Recommendation> {
+    let mut scored = results
+        .iter()
+        .filter(|entry| !system_dns_ips.contains(&entry.ip) && is_candidate(entry))
+        .filter_map(|entry| composite_score(entry).map(|score| (entry, score)))
+        .collect::<Vec<_>>();
+    scored.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let (primary, primary_score) = *scored.first()?;
+    let secondary = scored
+        .iter()
+        .skip(1)
+        .find(|(entry, _)| entry.name != primary.name)
+        .or_else(|| scored.get(1))
+        .copied();
+
+    let to_recommended = |entry: &RawResultEntry, score: f64| RecommendedServer {
+        name: entry.name.clone(),
+        ip: entry.ip,
+        bind: entry.bind,
+        score: round_millis(score),
+    };
+
+    let system_avg = mean(
+        &results
+            .iter()
+            .filter(|entry| system_dns_ips.contains(&entry.ip))
+            .filter_map(avg_duration_millis)
+            .collect::<Vec<_>>(),
+    );
+    let recommended_avg = mean(
+        &[Some(primary), secondary.map(|(entry, _)| entry)]
+            .into_iter()
+            .flatten()
+            .filter_map(avg_duration_millis)
+            .collect::<Vec<_>>(),
+    );
+    let system_dns_comparison = match (system_avg, recommended_avg) {
+        (Some(system_avg), Some(recommended_avg)) if system_avg > 0.0 => {
+            Some(SystemDnsComparison {
+                system_avg_duration_ms: round_millis(system_avg),
+                recommended_avg_duration_ms: round_millis(recommended_avg),
+                improvement_percentage: round_millis(
+                    (system_avg - recommended_avg) / system_avg * 100.0,
+                ),
+            })
+        }
+        _ => None,
+    };
+
+    Some(Recommendation {
+        primary: to_recommended(primary, primary_score),
+        secondary: secondary.map(|(entry, score)| to_recommended(entry, score)),
+        system_dns_comparison,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::MeasureResult;
+    use std::net::Ipv4Addr;
+
+    fn result_entry(name: &str, ip: [u8; 4], millis: &[Option<u64>]) -> RawResultEntry {
+        RawResultEntry::from(
+            millis
+                .iter()
+                .map(|m| MeasureResult {
+                    name: String::from(name),
+                    ip: IpAddr::V4(Ipv4Addr::from(ip)),
+                    bind: None,
+                    resolved_ip: IpAddr::V4(Ipv4Addr::new(142, 250, 75, 14)),
+                    time: match m {
+                        Some(m) => TimeResult::Succeeded(Duration::from_millis(*m)),
+                        None => TimeResult::Failed(String::from("Timeout")),
+                    },
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_composite_score() {
+        let stable = result_entry("Stable", [1, 1, 1, 1], &[Some(10), Some(10), Some(10)]);
+        assert_eq!(
+            composite_score(&stable),
+            Some(10.0 * (MEDIAN_WEIGHT + P90_WEIGHT))
+        );
+
+        // Same latencies but one failure out of two: score is divided by 0.5^2.
+        let flaky = result_entry("Flaky", [1, 0, 0, 1], &[Some(10), None]);
+        assert_eq!(
+            composite_score(&flaky),
+            Some(10.0 * (MEDIAN_WEIGHT + P90_WEIGHT) / 0.25)
+        );
+
+        let failed = result_entry("Failed", [10, 0, 0, 1], &[None, None]);
+        assert_eq!(composite_score(&failed), None);
+    }
+
+    #[test]
+    fn test_recommend_prefers_different_provider() {
+        let results = [
+            result_entry("Cloudflare", [1, 1, 1, 1], &[Some(5), Some(5)]),
+            result_entry("Cloudflare", [1, 0, 0, 1], &[Some(6), Some(6)]),
+            result_entry("Google", [8, 8, 8, 8], &[Some(8), Some(8)]),
+            result_entry("System DNS", [192, 168, 0, 1], &[Some(20), Some(20)]),
+            result_entry("Broken", [10, 0, 0, 1], &[None, None]),
+        ];
+        let system_dns_ips = [IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))];
+
+        let recommendation = recommend(&results, &system_dns_ips, |_| true).unwrap();
+
+        assert_eq!(recommendation.primary.name, "Cloudflare");
+        assert_eq!(
+            recommendation.primary.ip,
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))
+        );
+        let secondary = recommendation.secondary.unwrap();
+        assert_eq!(secondary.name, "Google");
+        assert_eq!(secondary.ip, IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)));
+        assert_eq!(
+            recommendation.system_dns_comparison,
+            Some(SystemDnsComparison {
+                system_avg_duration_ms: 20.0,
+                recommended_avg_duration_ms: 6.5,
+                improvement_percentage: 67.5,
+            })
+        );
+    }
+
+    #[test]
+    fn test_recommend_skips_system_dns() {
+        let results = [
+            result_entry("System DNS", [192, 168, 0, 1], &[Some(1), Some(1)]),
+            result_entry("Quad9", [9, 9, 9, 9], &[Some(12), Some(12)]),
+            result_entry("Google", [8, 8, 8, 8], &[Some(14), Some(14)]),
+        ];
+        let system_dns_ips = [IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))];
+
+        let recommendation = recommend(&results, &system_dns_ips, |_| true).unwrap();
+
+        assert_eq!(recommendation.primary.name, "Quad9");
+        assert_eq!(recommendation.secondary.unwrap().name, "Google");
+        assert_eq!(
+            recommendation
+                .system_dns_comparison
+                .unwrap()
+                .improvement_percentage,
+            -1200.0
+        );
+        assert_eq!(recommend(&results[..1], &system_dns_ips, |_| true), None);
+
+        // Entries the caller rules out, e.g. a local caching stub, are not recommended either.
+        let results = [
+            result_entry("Local cache", [127, 0, 0, 1], &[Some(1), Some(1)]),
+            result_entry("Quad9", [9, 9, 9, 9], &[Some(12), Some(12)]),
+        ];
+        let recommendation = recommend(&results, &[], |entry| !entry.ip.is_loopback()).unwrap();
+        assert_eq!(recommendation.primary.name, "Quad9");
+        assert_eq!(recommendation.secondary, None);
+    }
+
+    #[test]
+    fn test_recommend_single_provider() {
+        let results = [
+            result_entry("Cloudflare", [1, 1, 1, 1], &[Some(5), Some(5)]),
+            result_entry("Cloudflare", [1, 0, 0, 1], &[Some(6), Some(6)]),
+        ];
+
+        let recommendation = recommend(&results, &[], |_| true).unwrap();
+
+        assert_eq!(
+            recommendation.secondary.unwrap().ip,
+            IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1))
+        );
+        assert_eq!(recommendation.system_dns_comparison, None);
+        assert_eq!(recommend(&[], &[], |_| true), None);
+    }
+}
@@ -1,31 +1,379 @@
+use crate::system::get_interface_addr;
+use crate::system::ResolvConf;
+
 use hickory_resolver::config::LookupIpStrategy;
 use hickory_resolver::config::NameServerConfig;
 use hickory_resolver::config::Protocol;
 use hickory_resolver::config::ResolverConfig;
 use hickory_resolver::config::ResolverOpts;
+use hickory_resolver::config::ServerOrderingStrategy;
+use hickory_resolver::name_server::GenericConnector;
+use hickory_resolver::name_server::RuntimeProvider;
+use hickory_resolver::proto::iocompat::AsyncIoTokioAsStd;
+use hickory_resolver::proto::TokioTime;
+use hickory_resolver::system_conf::read_system_conf;
+use hickory_resolver::AsyncResolver;
+use hickory_resolver::Name;
 use hickory_resolver::Resolver;
+use hickory_resolver::TokioHandle;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
 use std::net::SocketAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::OnceLock;
 use std::time::Duration;
+use tokio::net::TcpSocket;
+use tokio::net::TcpStream;
+use tokio::net::UdpSocket;
+
+/// An async resolver whose sockets are bound to a [`QuerySource`].
+pub type BoundAsyncResolver = AsyncResolver<GenericConnector<BoundRuntimeProvider>>;
+
+/// The source of the queries of a resolver.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct QuerySource {
+    /// The source address of the queries over every protocol.
+    pub addr: Option<IpAddr>,
+    /// The network interface the sockets are bound to, so the queries leave through it
+    /// whatever the routing table says. Only set on Linux.
+    pub device: Option<String>,
+}
+
+/// The Tokio runtime provider of hickory, with sockets bound to a [`QuerySource`].
+///
+/// The Tokio provider binds only UDP sockets to the source address of the name server
+/// configuration and connects TCP, TLS and HTTPS from any address.
+#[derive(Clone, Default)]
+pub struct BoundRuntimeProvider {
+    handle: TokioHandle,
+    source: QuerySource,
+}
+
+impl RuntimeProvider for BoundRuntimeProvider {
+    type Handle = TokioHandle;
+    type Timer = TokioTime;
+    type Udp = UdpSocket;
+    type Tcp = AsyncIoTokioAsStd<TcpStream>;
+
+    fn create_handle(&self) -> Self::Handle {
+        self.handle.clone()
+    }
+
+    fn connect_tcp(
+        &self,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+        let source = self.source.clone();
+        Box::pin(async move {
+            let socket = match server_addr {
+                SocketAddr::V4(_) => TcpSocket::new_v4()?,
+                SocketAddr::V6(_) => TcpSocket::new_v6()?,
+            };
+            #[cfg(target_os = "linux")]
+            if let Some(device) = &source.device {
+                socket.bind_device(Some(device.as_bytes()))?;
+            }
+            if let Some(addr) = source.addr {
+                socket.bind(SocketAddr::new(addr, 0))?;
+            }
+            socket.connect(server_addr).await.map(AsyncIoTokioAsStd)
+        })
+    }
 
+    fn bind_udp(
+        &self,
+        local_addr: SocketAddr,
+        _server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+        let source = self.source.clone();
+        Box::pin(async move {
+            let socket = UdpSocket::bind(local_addr).await?;
+            #[cfg(target_os = "linux")]
+            if let Some(device) = &source.device {
+                socket.bind_device(Some(device.as_bytes()))?;
+            }
+            #[cfg(not(target_os = "linux"))]
+            let _ = source;
+            Ok(socket)
+        })
+    }
+}
+
+fn bound_async_resolver(
+    resolver_config: ResolverConfig,
+    resolver_opts: ResolverOpts,
+    source: QuerySource,
+) -> BoundAsyncResolver {
+    let provider = BoundRuntimeProvider {
+        handle: TokioHandle::default(),
+        source,
+    };
+    AsyncResolver::new(
+        resolver_config,
+        resolver_opts,
+        GenericConnector::new(provider),
+    )
+}
+
+/// Create a resolver for a single name server.
+///
+/// Encrypted protocols validate the certificate against `tls_dns_name`,
+/// falling back to the IP address of the server when no name is given.
+/// Queries are sent from `bind_addr` when given.
 pub fn create_resolver(
     socket_addr: SocketAddr,
     protocol: Protocol,
+    tls_dns_name: Option<String>,
+    bind_addr: Option<IpAddr>,
     timeout_millis: u64,
     lookup_ip: LookupIpStrategy,
 ) -> Resolver {
+    let (resolver_config, resolver_opts) = single_server_config(
+        socket_addr,
+        protocol,
+        tls_dns_name,
+        bind_addr,
+        timeout_millis,
+        lookup_ip,
+    );
+
+    Resolver::new(resolver_config, resolver_opts).unwrap()
+}
+
+/// Create an async resolver for a single name server, see [`create_resolver`].
+///
+/// It runs on the Tokio runtime it is used from. Answers are not cached, so every lookup
+/// reaches the server while its connections are reused between lookups. Queries over every
+/// protocol are sent from `source`.
+pub fn create_async_resolver(
+    socket_addr: SocketAddr,
+    protocol: Protocol,
+    tls_dns_name: Option<String>,
+    source: QuerySource,
+    timeout_millis: u64,
+    lookup_ip: LookupIpStrategy,
+) -> BoundAsyncResolver {
+    let (resolver_config, resolver_opts) = single_server_config(
+        socket_addr,
+        protocol,
+        tls_dns_name,
+        source.addr,
+        timeout_millis,
+        lookup_ip,
+    );
+
+    bound_async_resolver(resolver_config, resolver_opts, source)
+}
+
+fn single_server_config(
+    socket_addr: SocketAddr,
+    protocol: Protocol,
+    tls_dns_name: Option<String>,
+    bind_addr: Option<IpAddr>,
+    timeout_millis: u64,
+    lookup_ip: LookupIpStrategy,
+) -> (ResolverConfig, ResolverOpts) {
     let mut resolver_config = ResolverConfig::new();
-    resolver_config.add_name_server(NameServerConfig {
+    resolver_config.add_name_server(name_server_config(
         socket_addr,
         protocol,
-        tls_dns_name: None,
-        trust_negative_responses: false,
-        bind_addr: None,
-    });
+        tls_dns_name,
+        bind_addr,
+    ));
 
     let mut resolver_opts = ResolverOpts::default();
     resolver_opts.attempts = 0;
     resolver_opts.timeout = Duration::from_millis(timeout_millis);
     resolver_opts.ip_strategy = lookup_ip;
+    resolver_opts.cache_size = 0;
 
-    Resolver::new(resolver_config, resolver_opts).unwrap()
+    (resolver_config, resolver_opts)
+}
+
+/// Create an async resolver which queries the name servers the way glibc does with the given resolv.conf.
+///
+/// Every name server is tried in order for `timeout_millis`, and the whole list is tried
+/// `attempts` times. Names with fewer dots than `ndots` are looked up with the search domains first.
+pub fn create_system_like_resolver(
+    socket_addrs: &[SocketAddr],
+    protocol: Protocol,
+    tls_dns_name: Option<String>,
+    source: QuerySource,
+    timeout_millis: u64,
+    resolv_conf: &ResolvConf,
+    lookup_ip: LookupIpStrategy,
+) -> BoundAsyncResolver {
+    let options = &resolv_conf.options;
+    let protocol = match protocol {
+        Protocol::Udp if options.use_vc => Protocol::Tcp,
+        protocol => protocol,
+    };
+    let search = resolv_conf
+        .search
+        .iter()
+        .filter_map(|domain| Name::from_str(domain).ok())
+        .collect();
+    let name_servers = socket_addrs
+        .iter()
+        .map(|socket_addr| {
+            name_server_config(*socket_addr, protocol, tls_dns_name.clone(), source.addr)
+        })
+        .collect::<Vec<_>>();
+    let resolver_config = ResolverConfig::from_parts(None, search, name_servers);
+
+    let mut resolver_opts = ResolverOpts::default();
+    resolver_opts.attempts = usize::from(options.attempts.saturating_sub(1));
+    resolver_opts.timeout = Duration::from_millis(timeout_millis);
+    resolver_opts.ndots = usize::from(options.ndots);
+    resolver_opts.edns0 = options.edns0;
+    resolver_opts.ip_strategy = lookup_ip;
+    resolver_opts.cache_size = 0;
+    // glibc waits for each server before failing over to the next one.
+    resolver_opts.num_concurrent_reqs = 1;
+    resolver_opts.server_ordering_strategy = ServerOrderingStrategy::UserProvidedOrder;
+
+    bound_async_resolver(resolver_config, resolver_opts, source)
+}
+
+fn name_server_config(
+    socket_addr: SocketAddr,
+    protocol: Protocol,
+    tls_dns_name: Option<String>,
+    bind_addr: Option<IpAddr>,
+) -> NameServerConfig {
+    NameServerConfig {
+        socket_addr,
+        protocol,
+        tls_dns_name: if protocol.is_encrypted() {
+            Some(tls_dns_name.unwrap_or_else(|| socket_addr.ip().to_string()))
+        } else {
+            None
+        },
+        trust_negative_responses: false,
+        tls_config: None,
+        bind_addr: bind_addr.map(|ip| SocketAddr::new(ip, 0)),
+    }
+}
+
+/// The source of the queries to a server, an address or a network interface.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum BindTarget {
+    Addr(IpAddr),
+    Interface(String),
+}
+
+impl BindTarget {
+    /// The address to bind to, the one of the given IP version for an interface.
+    ///
+    /// On Linux the sockets are also bound to the interface, which is checked here.
+    pub fn resolve(&self, ip: crate::args::IpAddr) -> io::Result<IpAddr> {
+        match self {
+            BindTarget::Addr(addr) => Ok(*addr),
+            BindTarget::Interface(name) => {
+                let addr = get_interface_addr(name, ip)?;
+                #[cfg(target_os = "linux")]
+                TcpSocket::new_v4()?
+                    .bind_device(Some(name.as_bytes()))
+                    .map_err(|e| {
+                        io::Error::new(
+                            e.kind(),
+                            format!("Cannot bind to network interface {name}: {e}"),
+                        )
+                    })?;
+                Ok(addr)
+            }
+        }
+    }
+
+    /// The network interface the sockets are bound to, only Linux binds sockets to one.
+    pub fn device(&self) -> Option<&str> {
+        match self {
+            BindTarget::Interface(name) if cfg!(target_os = "linux") => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BindTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindTarget::Addr(addr) => write!(f, "{addr}"),
+            BindTarget::Interface(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+/// Resolves host names of name servers into their addresses.
+pub trait HostResolver {
+    /// Addresses of `hostname` of the given IP version, empty when resolution fails.
+    fn resolve(&self, hostname: &str, ip: crate::args::IpAddr) -> Vec<IpAddr>;
+}
+
+/// Resolves host names through the system resolver, or a given bootstrap server.
+pub struct BootstrapResolver {
+    server: Option<IpAddr>,
+    timeout_millis: u64,
+    /// The resolvers of IPv4 and IPv6 addresses, created on first use.
+    resolvers: [OnceLock<Result<Resolver, String>>; 2],
+}
+
+impl BootstrapResolver {
+    pub fn new(server: Option<IpAddr>, timeout_millis: u64) -> Self {
+        Self {
+            server,
+            timeout_millis,
+            resolvers: [OnceLock::new(), OnceLock::new()],
+        }
+    }
+
+    fn resolver(&self, ip: crate::args::IpAddr) -> Result<&Resolver, &str> {
+        let index = match ip {
+            crate::args::IpAddr::V4 => 0,
+            crate::args::IpAddr::V6 => 1,
+        };
+        self.resolvers[index]
+            .get_or_init(|| self.create(ip).map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(String::as_str)
+    }
+
+    fn create(&self, ip: crate::args::IpAddr) -> Result<Resolver, std::io::Error> {
+        let lookup_ip = LookupIpStrategy::from(ip);
+        match self.server {
+            Some(server) => Ok(create_resolver(
+                SocketAddr::new(server, 53),
+                Protocol::Udp,
+                None,
+                None,
+                self.timeout_millis,
+                lookup_ip,
+            )),
+            None => {
+                let (resolver_config, mut resolver_opts) = read_system_conf().unwrap_or_default();
+                resolver_opts.timeout = Duration::from_millis(self.timeout_millis);
+                resolver_opts.ip_strategy = lookup_ip;
+                Resolver::new(resolver_config, resolver_opts)
+            }
+        }
+    }
+}
+
+impl HostResolver for BootstrapResolver {
+    fn resolve(&self, hostname: &str, ip: crate::args::IpAddr) -> Vec<IpAddr> {
+        let lookup = self
+            .resolver(ip)
+            .map_err(String::from)
+            .and_then(|resolver| resolver.lookup_ip(hostname).map_err(|e| e.to_string()));
+        match lookup {
+            Ok(lookup) => lookup.iter().collect(),
+            Err(e) => {
+                eprintln!("Failed to resolve {hostname}: {e}");
+                Vec::new()
+            }
+        }
+    }
 }
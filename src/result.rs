@@ -1,3 +1,6 @@
+use hickory_resolver::error::ResolveError;
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::proto::op::ResponseCode;
 use std::fmt;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
@@ -8,6 +11,9 @@ use tabled::settings as tabled_settings;
 // TimeResult
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// Error of a request the server answered with REFUSED, which the error of the resolver does not name.
+const REFUSED_ERROR: &str = "Query refused (REFUSED)";
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, derive_more::IsVariant)]
 #[cfg_attr(test, derive(Eq, PartialEq))]
 pub enum TimeResult {
@@ -18,6 +24,17 @@ pub enum TimeResult {
 }
 
 impl TimeResult {
+    /// The failure of a request from the error of the resolver.
+    pub fn from_resolve_error(error: &ResolveError) -> Self {
+        match error.kind() {
+            ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::Refused,
+                ..
+            } => TimeResult::Failed(String::from(REFUSED_ERROR)),
+            _ => TimeResult::Failed(error.to_string()),
+        }
+    }
+
     pub fn get_xml_type_str(&self) -> &str {
         match self {
             TimeResult::Succeeded(_) => "succeeded",
@@ -55,6 +72,14 @@ impl TimeResult {
             }
         }
     }
+
+    pub fn is_refused(&self) -> bool {
+        match self {
+            TimeResult::Succeeded(_) => false,
+            // Not an OS `Connection refused` error of a server which is down.
+            TimeResult::Failed(error) => error == REFUSED_ERROR,
+        }
+    }
 }
 
 impl fmt::Display for TimeResult {
@@ -68,11 +93,62 @@ impl fmt::Display for TimeResult {
 
 impl From<TimeResult> for tabled_settings::Color {
     fn from(value: TimeResult) -> Self {
-        match value {
+        ColorThresholds::default().latency_color(&value)
+    }
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// ColorThresholds
+//////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Factor applied to the fastest average duration to get the good latency threshold in relative mode.
+const RELATIVE_GOOD_LATENCY_FACTOR: f64 = 1.5;
+/// Factor applied to the fastest average duration to get the fair latency threshold in relative mode.
+const RELATIVE_FAIR_LATENCY_FACTOR: f64 = 3.0;
+
+/// Bands used to color durations and success rates in the output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ColorThresholds {
+    /// Durations up to this value are colored green.
+    pub good_latency: Duration,
+    /// Durations up to this value are colored yellow, slower ones red.
+    pub fair_latency: Duration,
+    /// Success rates (in percent) at or above this value are colored green.
+    pub good_success_rate: u8,
+    /// Success rates at or above this value are colored yellow.
+    pub fair_success_rate: u8,
+    /// Success rates at or above this value are colored bright red, lower ones red.
+    pub poor_success_rate: u8,
+}
+
+impl Default for ColorThresholds {
+    fn default() -> Self {
+        ColorThresholds {
+            good_latency: Duration::from_millis(30),
+            fair_latency: Duration::from_millis(80),
+            good_success_rate: 100,
+            fair_success_rate: 50,
+            poor_success_rate: 20,
+        }
+    }
+}
+
+impl ColorThresholds {
+    /// Replace the latency bands with ones computed relative to the fastest average duration of the run.
+    pub fn relative_to(self, fastest: Duration) -> Self {
+        ColorThresholds {
+            good_latency: fastest.mul_f64(RELATIVE_GOOD_LATENCY_FACTOR),
+            fair_latency: fastest.mul_f64(RELATIVE_FAIR_LATENCY_FACTOR),
+            ..self
+        }
+    }
+
+    pub fn latency_color(&self, time: &TimeResult) -> tabled_settings::Color {
+        match time {
             TimeResult::Succeeded(duration) => {
-                if duration.as_millis() <= 30 {
+                if *duration <= self.good_latency {
                     tabled_settings::Color::FG_BRIGHT_GREEN
-                } else if duration.as_millis() <= 80 {
+                } else if *duration <= self.fair_latency {
                     tabled_settings::Color::FG_BRIGHT_YELLOW
                 } else {
                     tabled_settings::Color::FG_BRIGHT_RED
@@ -81,6 +157,18 @@ impl From<TimeResult> for tabled_settings::Color {
             TimeResult::Failed(_) => tabled_settings::Color::FG_RED,
         }
     }
+
+    pub fn success_rate_color(&self, percentage: f32) -> tabled_settings::Color {
+        if percentage >= f32::from(self.good_success_rate) {
+            tabled_settings::Color::FG_BRIGHT_GREEN
+        } else if percentage >= f32::from(self.fair_success_rate) {
+            tabled_settings::Color::FG_BRIGHT_YELLOW
+        } else if percentage >= f32::from(self.poor_success_rate) {
+            tabled_settings::Color::FG_BRIGHT_RED
+        } else {
+            tabled_settings::Color::FG_RED
+        }
+    }
 }
 
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -91,6 +179,8 @@ impl From<TimeResult> for tabled_settings::Color {
 pub struct MeasureResult {
     pub name: String,
     pub ip: IpAddr,
+    /// Source address the query was sent from, when not left to the OS.
+    pub bind: Option<IpAddr>,
     pub resolved_ip: IpAddr,
     pub time: TimeResult,
 }
@@ -99,10 +189,15 @@ pub struct MeasureResult {
 // RawResultEntry
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// A run of this many timeouts right after a successful request is counted as a timeout burst.
+const TIMEOUT_BURST_LENGTH: usize = 3;
+
 #[derive(Debug, Clone)]
 pub struct RawResultEntry {
     pub name: String,
     pub ip: IpAddr,
+    /// Source address the queries were sent from, when not left to the OS.
+    pub bind: Option<IpAddr>,
     pub last_resolved_ip: IpAddr,
     pub total_requests: i32,
     pub successful_requests: i32,
@@ -114,6 +209,14 @@ pub struct RawResultEntry {
     pub max_duration_color: tabled_settings::Color,
     pub avg_duration: TimeResult,
     pub avg_duration_color: tabled_settings::Color,
+    /// Durations of the successful requests in the order they were made.
+    pub durations: Vec<Duration>,
+    /// Whether the run was interrupted before all requests to the server were made.
+    pub incomplete: bool,
+    /// Number of requests the server answered with REFUSED.
+    pub refused_requests: i32,
+    /// Number of sudden timeout runs after successful requests.
+    pub timeout_bursts: i32,
 }
 
 impl From<Vec<MeasureResult>> for RawResultEntry {
@@ -125,13 +228,32 @@ impl From<Vec<MeasureResult>> for RawResultEntry {
         // Compute min/max on successful requests
         let mut min_dur: Option<Duration> = None;
         let mut max_dur: Option<Duration> = None;
+        let mut durations = Vec::new();
+
+        // Rate limiting shows up as REFUSED answers or timeouts right after successful requests.
+        let mut refused_requests = 0;
+        let mut timeout_bursts = 0;
+        let mut timeouts_after_success: Option<usize> = None;
 
         for measure_result in &value {
+            if measure_result.time.is_refused() {
+                refused_requests += 1;
+            }
+            timeouts_after_success = match &measure_result.time {
+                TimeResult::Succeeded(_) => Some(0),
+                time if time.is_timeout() => timeouts_after_success.map(|timeouts| timeouts + 1),
+                TimeResult::Failed(_) => None,
+            };
+            if timeouts_after_success == Some(TIMEOUT_BURST_LENGTH) {
+                timeout_bursts += 1;
+            }
+
             match measure_result.time {
                 TimeResult::Succeeded(duration) => {
                     successful_requests += 1;
                     total_time += duration;
                     last_resolved_ip = measure_result.resolved_ip;
+                    durations.push(duration);
 
                     min_dur = Some(match min_dur {
                         Some(current_min) => current_min.min(duration),
@@ -166,19 +288,13 @@ impl From<Vec<MeasureResult>> for RawResultEntry {
 
         let successful_requests_percentage =
             successful_requests as f32 / value.len() as f32 * 100.0;
-        let successful_requests_color = if successful_requests_percentage == 100.0 {
-            tabled_settings::Color::FG_BRIGHT_GREEN
-        } else if successful_requests_percentage >= 50.0 {
-            tabled_settings::Color::FG_BRIGHT_YELLOW
-        } else if successful_requests_percentage >= 20.0 {
-            tabled_settings::Color::FG_BRIGHT_RED
-        } else {
-            tabled_settings::Color::FG_RED
-        };
+        let successful_requests_color =
+            ColorThresholds::default().success_rate_color(successful_requests_percentage);
 
         RawResultEntry {
             name: value[0].name.clone(),
             ip: value[0].ip,
+            bind: value[0].bind,
             last_resolved_ip,
             total_requests: value.len() as i32,
             successful_requests,
@@ -190,10 +306,54 @@ impl From<Vec<MeasureResult>> for RawResultEntry {
             max_duration_color: max_duration.into(),
             avg_duration: avg_duration.clone(),
             avg_duration_color: avg_duration.clone().into(),
+            durations,
+            incomplete: false,
+            refused_requests,
+            timeout_bursts,
         }
     }
 }
 
+impl RawResultEntry {
+    /// Nearest-rank percentile of the successful durations, `percentile` is in range 0..=100.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.durations.is_empty() {
+            return None;
+        }
+        let mut sorted = self.durations.clone();
+        sorted.sort();
+        let rank = (percentile / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    /// Mean absolute difference between consecutive successful durations.
+    pub fn jitter(&self) -> Option<Duration> {
+        if self.durations.len() < 2 {
+            return None;
+        }
+        let total = self
+            .durations
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .sum::<Duration>();
+        Some(total / (self.durations.len() - 1) as u32)
+    }
+
+    /// Whether the server answered in a way that suggests it rate limited the requests.
+    pub fn is_rate_limited(&self) -> bool {
+        self.refused_requests > 0 || self.timeout_bursts > 0
+    }
+
+    /// Recompute all colors of the entry using the given thresholds.
+    pub fn apply_color_thresholds(&mut self, thresholds: &ColorThresholds) {
+        self.successful_requests_color =
+            thresholds.success_rate_color(self.successful_requests_percentage);
+        self.min_duration_color = thresholds.latency_color(&self.min_duration);
+        self.max_duration_color = thresholds.latency_color(&self.max_duration);
+        self.avg_duration_color = thresholds.latency_color(&self.avg_duration);
+    }
+}
+
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
 // Tests
 //////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -201,8 +361,6 @@ impl From<Vec<MeasureResult>> for RawResultEntry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hickory_resolver::error::ResolveError;
-    use hickory_resolver::error::ResolveErrorKind;
     use hickory_resolver::proto::error::ProtoError;
     use hickory_resolver::proto::error::ProtoErrorKind;
     use std::io;
@@ -227,24 +385,44 @@ mod tests {
         assert!(!TimeResult::Succeeded(Duration::new(0, 100)).is_timeout());
     }
 
+    #[test]
+    fn test_time_result_is_refused() {
+        let refused = ResolveError::from(ResolveErrorKind::NoRecordsFound {
+            query: Box::default(),
+            soa: None,
+            negative_ttl: None,
+            response_code: ResponseCode::Refused,
+            trusted: false,
+        });
+        assert!(TimeResult::from_resolve_error(&refused).is_refused());
+        assert!(!TimeResult::Failed(
+            ResolveError::from(io::Error::from(io::ErrorKind::ConnectionRefused)).to_string()
+        )
+        .is_refused());
+        assert!(!TimeResult::Succeeded(Duration::new(0, 100)).is_refused());
+    }
+
     #[test]
     fn test_raw_result_entry_from() {
         let measure_results = vec![
             MeasureResult {
                 name: String::from("Google"),
                 ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                bind: None,
                 resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
                 time: TimeResult::Succeeded(Duration::new(0, 100)),
             },
             MeasureResult {
                 name: String::from("Google"),
                 ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                bind: None,
                 resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
                 time: TimeResult::Succeeded(Duration::new(0, 200)),
             },
             MeasureResult {
                 name: String::from("Google"),
                 ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                bind: None,
                 resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
                 time: TimeResult::Failed(String::from("Timeout")),
             },
@@ -292,5 +470,108 @@ mod tests {
             result_entry.avg_duration_color,
             tabled_settings::Color::FG_BRIGHT_GREEN
         );
+        // Samples
+        assert_eq!(
+            result_entry.durations,
+            vec![Duration::new(0, 100), Duration::new(0, 200)]
+        );
+        assert_eq!(result_entry.percentile(50.0), Some(Duration::new(0, 100)));
+        assert_eq!(result_entry.percentile(90.0), Some(Duration::new(0, 200)));
+        assert_eq!(result_entry.jitter(), Some(Duration::new(0, 100)));
+    }
+
+    #[test]
+    fn test_raw_result_entry_rate_limit_indicators() {
+        let measure_result = |time: TimeResult| MeasureResult {
+            name: String::from("Limited"),
+            ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            bind: None,
+            resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+            time,
+        };
+        let succeeded = || measure_result(TimeResult::Succeeded(Duration::from_millis(10)));
+        let timeout = || {
+            measure_result(TimeResult::Failed(
+                ResolveError::from(ResolveErrorKind::Timeout).to_string(),
+            ))
+        };
+
+        // Timeouts from the start are an unresponsive server, not a burst.
+        let result_entry = RawResultEntry::from(vec![timeout(), timeout(), timeout(), succeeded()]);
+        assert_eq!(result_entry.timeout_bursts, 0);
+        assert!(!result_entry.is_rate_limited());
+
+        let result_entry = RawResultEntry::from(vec![
+            succeeded(),
+            timeout(),
+            timeout(),
+            timeout(),
+            timeout(),
+            succeeded(),
+            timeout(),
+            timeout(),
+            timeout(),
+            measure_result(TimeResult::Failed(String::from(REFUSED_ERROR))),
+        ]);
+        assert_eq!(result_entry.timeout_bursts, 2);
+        assert_eq!(result_entry.refused_requests, 1);
+        assert!(result_entry.is_rate_limited());
+    }
+
+    #[test]
+    fn test_raw_result_entry_apply_color_thresholds() {
+        let mut result_entry = RawResultEntry::from(vec![
+            MeasureResult {
+                name: String::from("Satellite"),
+                ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                bind: None,
+                resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                time: TimeResult::Succeeded(Duration::from_millis(600)),
+            },
+            MeasureResult {
+                name: String::from("Satellite"),
+                ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                bind: None,
+                resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                time: TimeResult::Succeeded(Duration::from_millis(1000)),
+            },
+            MeasureResult {
+                name: String::from("Satellite"),
+                ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                bind: None,
+                resolved_ip: IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)),
+                time: TimeResult::Failed(String::from("Timeout")),
+            },
+        ]);
+        assert_eq!(
+            result_entry.avg_duration_color,
+            tabled_settings::Color::FG_BRIGHT_RED
+        );
+
+        let thresholds = ColorThresholds {
+            fair_success_rate: 70,
+            ..ColorThresholds::default()
+        }
+        .relative_to(Duration::from_millis(500));
+        assert_eq!(thresholds.good_latency, Duration::from_millis(750));
+        assert_eq!(thresholds.fair_latency, Duration::from_millis(1500));
+
+        result_entry.apply_color_thresholds(&thresholds);
+        assert_eq!(
+            result_entry.successful_requests_color,
+            tabled_settings::Color::FG_BRIGHT_RED
+        );
+        assert_eq!(
+            result_entry.min_duration_color,
+            tabled_settings::Color::FG_BRIGHT_GREEN
+        );
+        assert_eq!(
+            result_entry.max_duration_color,
+            tabled_settings::Color::FG_BRIGHT_YELLOW
+        );
+        assert_eq!(
+            result_entry.avg_duration_color,
+            tabled_settings::Color::FG_BRIGHT_YELLOW
+        );
     }
 }
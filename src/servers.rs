@@ -1,111 +1,360 @@
+use crate::args::Protocol;
+use crate::resolver::BindTarget;
+use crate::resolver::QuerySource;
+
 use std::net;
 
 #[derive(Debug, Clone)]
 pub struct DnsEntry {
     pub name: String,
     pub socket_addr: net::SocketAddr,
+    /// Protocol used for this server instead of the configured one.
+    pub protocol: Option<Protocol>,
+    /// Name to validate the certificate against for DNS over TLS and HTTPS.
+    pub tls_dns_name: Option<String>,
+    /// Properties of the server such as `filtering` or `no-logging`.
+    pub tags: Vec<String>,
+    /// Host name the address was resolved from.
+    pub hostname: Option<String>,
+    /// Timeout in seconds used for this server instead of the configured one.
+    pub timeout: Option<u64>,
+    /// Number of requests made to this server instead of the configured one.
+    pub requests: Option<u16>,
+    /// Addresses queried in order when `socket_addr` does not answer.
+    pub fallback_addrs: Vec<net::SocketAddr>,
+    /// Source of the queries to this server instead of the configured one.
+    pub bind: Option<BindTarget>,
+    /// Network interface the sockets are bound to, kept from `bind` when it is resolved.
+    pub device: Option<String>,
+    /// Maximum queries per second to this server instead of the configured one.
+    pub qps: Option<u32>,
+    /// Minimum milliseconds between requests to this server instead of the configured one.
+    pub interval: Option<u64>,
+}
+
+impl DnsEntry {
+    pub fn new(name: String, socket_addr: net::SocketAddr) -> Self {
+        Self {
+            name,
+            socket_addr,
+            protocol: None,
+            tls_dns_name: None,
+            tags: Vec::new(),
+            hostname: None,
+            timeout: None,
+            requests: None,
+            fallback_addrs: Vec::new(),
+            bind: None,
+            device: None,
+            qps: None,
+            interval: None,
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// The source address of the queries, interfaces are resolved to their address when the
+    /// servers of a benchmark are collected.
+    pub fn bind_addr(&self) -> Option<net::IpAddr> {
+        match self.bind {
+            Some(BindTarget::Addr(bind_addr)) => Some(bind_addr),
+            _ => None,
+        }
+    }
+
+    /// The source of the queries, see [`DnsEntry::bind_addr`].
+    pub fn query_source(&self) -> QuerySource {
+        QuerySource {
+            addr: self.bind_addr(),
+            device: self.device.clone(),
+        }
+    }
+
+    /// Whether the system configuration can point at this server as benchmarked: plain DNS on
+    /// port 53 of a single non-loopback address. Loopback servers are local stubs or caches.
+    pub fn is_applicable(&self, protocol: Protocol) -> bool {
+        matches!(
+            self.protocol.unwrap_or(protocol),
+            Protocol::Udp | Protocol::Tcp
+        ) && self.socket_addr.port() == 53
+            && !self.socket_addr.ip().is_loopback()
+            && self.fallback_addrs.is_empty()
+    }
+}
+
+/// Blocks malicious or unwanted domains.
+pub const TAG_FILTERING: &str = "filtering";
+/// Blocks adult content.
+pub const TAG_FAMILY_SAFE: &str = "family-safe";
+/// Blocks ads and trackers.
+pub const TAG_AD_BLOCKING: &str = "ad-blocking";
+/// The provider states that queries are not logged.
+pub const TAG_NO_LOGGING: &str = "no-logging";
+/// Served from many locations under the same address.
+pub const TAG_ANYCAST: &str = "anycast";
+/// Supports privacy features such as DNS over TLS/HTTPS or QNAME minimisation.
+pub const TAG_PRIVACY: &str = "privacy";
+
+/// Include and exclude filters of the benchmarked servers, names and tags are case-insensitive.
+#[derive(Debug, Clone, Default)]
+pub struct DnsEntryFilter {
+    /// Keep servers with at least one of these tags.
+    pub include_tags: Vec<String>,
+    /// Drop servers with any of these tags.
+    pub exclude_tags: Vec<String>,
+    /// Keep servers with one of these names.
+    pub only: Vec<String>,
+    /// Drop servers with one of these names or IP addresses.
+    pub exclude: Vec<String>,
+}
+
+impl DnsEntryFilter {
+    pub fn matches(&self, entry: &DnsEntry) -> bool {
+        let is_included =
+            self.include_tags.is_empty() || self.include_tags.iter().any(|tag| entry.has_tag(tag));
+        let is_tag_excluded = self.exclude_tags.iter().any(|tag| entry.has_tag(tag));
+
+        is_included && !is_tag_excluded && self.matches_name_or_ip(entry)
+    }
+
+    /// Whether the entry passes the name and IP address filters, ignoring the tags.
+    ///
+    /// The detected system and gateway DNS servers have no tags, so only these filters
+    /// apply to them.
+    pub fn matches_name_or_ip(&self, entry: &DnsEntry) -> bool {
+        let is_only = self.only.is_empty()
+            || self
+                .only
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&entry.name));
+        let is_excluded = self.exclude.iter().any(|value| {
+            value.eq_ignore_ascii_case(&entry.name)
+                || value.parse::<net::IpAddr>().ok() == Some(entry.socket_addr.ip())
+        });
+
+        is_only && !is_excluded
+    }
 }
 
 macro_rules! ipv4_dns_entry {
-    ($name:expr, $ip:expr, $port:expr) => {
+    ($name:expr, $ip:expr, $port:expr, [$($tag:expr),*]) => {
         DnsEntry {
-            name: String::from($name),
-            socket_addr: net::SocketAddr::new(
-                net::IpAddr::V4(net::Ipv4Addr::new($ip.0, $ip.1, $ip.2, $ip.3)),
-                $port,
-            ),
+            tags: vec![$(String::from($tag)),*],
+            ..DnsEntry::new(
+                String::from($name),
+                net::SocketAddr::new(
+                    net::IpAddr::V4(net::Ipv4Addr::new($ip.0, $ip.1, $ip.2, $ip.3)),
+                    $port,
+                ),
+            )
         }
     };
 }
 
 macro_rules! ipv6_dns_entry {
-    ($name:expr, $ip:expr, $port:expr) => {
+    ($name:expr, $ip:expr, $port:expr, [$($tag:expr),*]) => {
         DnsEntry {
-            name: String::from($name),
-            socket_addr: net::SocketAddr::new(
-                net::IpAddr::V6(net::Ipv6Addr::new(
-                    $ip.0, $ip.1, $ip.2, $ip.3, $ip.4, $ip.5, $ip.6, $ip.7,
-                )),
-                $port,
-            ),
+            tags: vec![$(String::from($tag)),*],
+            ..DnsEntry::new(
+                String::from($name),
+                net::SocketAddr::new(
+                    net::IpAddr::V6(net::Ipv6Addr::new(
+                        $ip.0, $ip.1, $ip.2, $ip.3, $ip.4, $ip.5, $ip.6, $ip.7,
+                    )),
+                    $port,
+                ),
+            )
         }
     };
 }
 
 lazy_static::lazy_static! {
     pub static ref IPV4_DNS_ENTRIES: Vec<DnsEntry> = vec![
-        ipv4_dns_entry!("Google", (8, 8, 8, 8), 53),
-        ipv4_dns_entry!("Google", (8, 8, 4, 4), 53),
-        ipv4_dns_entry!("Cloudflare", (1, 1, 1, 1), 53),
-        ipv4_dns_entry!("Cloudflare", (1, 0, 0, 1), 53),
-        ipv4_dns_entry!("Quad9", (9, 9, 9, 9), 53),
-        ipv4_dns_entry!("Quad9", (149, 112, 112, 112), 53),
-        ipv4_dns_entry!("Control D", (76, 76, 2, 0), 53),
-        ipv4_dns_entry!("Control D", (76, 76, 10, 0), 53),
-        ipv4_dns_entry!("OpenDNS Home", (208, 67, 222, 222), 53),
-        ipv4_dns_entry!("OpenDNS Home", (208, 67, 220, 220), 53),
-        ipv4_dns_entry!("CleanBrowsing", (185, 228, 168, 9), 53),
-        ipv4_dns_entry!("CleanBrowsing", (185, 228, 169, 9), 53),
-        ipv4_dns_entry!("AdGuard DNS", (94, 140, 14, 14), 53),
-        ipv4_dns_entry!("AdGuard DNS", (94, 140, 15, 15), 53),
-        ipv4_dns_entry!("Comodo Secure DNS", (8, 26, 56, 26), 53),
-        ipv4_dns_entry!("Comodo Secure DNS", (8, 20, 247, 20), 53),
-        ipv4_dns_entry!("Level3", (209, 244, 0, 3), 53),
-        ipv4_dns_entry!("Level3", (209, 244, 0, 4), 53),
-        ipv4_dns_entry!("Verisign", (64, 6, 64, 6), 53),
-        ipv4_dns_entry!("Verisign", (64, 6, 65, 6), 53),
-        ipv4_dns_entry!("DNS.WATCH", (84, 200, 69, 80), 53),
-        ipv4_dns_entry!("DNS.WATCH", (84, 200, 70, 40), 53),
-        ipv4_dns_entry!("Norton ConnectSafe", (199, 85, 126, 10), 53),
-        ipv4_dns_entry!("Norton ConnectSafe", (199, 85, 127, 10), 53),
-        ipv4_dns_entry!("SafeDNS", (195, 46, 39, 39), 53),
-        ipv4_dns_entry!("SafeDNS", (195, 46, 39, 40), 53),
-        ipv4_dns_entry!("NextDNS", (45, 90, 28, 100), 53),
-        ipv4_dns_entry!("NextDNS", (45, 90, 30, 100), 53),
-        ipv4_dns_entry!("Dyn", (216, 146, 35, 35), 53),
-        ipv4_dns_entry!("Dyn", (216, 146, 36, 36), 53),
-        ipv4_dns_entry!("Hurricane Electric", (74, 82, 42, 42), 53),
-        ipv4_dns_entry!("Surfshark DNS", (162, 252, 172, 57), 53),
-        ipv4_dns_entry!("Surfshark DNS", (149, 154, 159, 92), 53),
-        ipv4_dns_entry!("SafeServe", (198, 54, 117, 10), 53),
-        ipv4_dns_entry!("SafeServe", (198, 54, 117, 11), 53),
-        ipv4_dns_entry!("Vercara UltraDNS Public", (156, 154, 70, 2), 53),
-        ipv4_dns_entry!("Vercara UltraDNS Public", (156, 154, 71, 2), 53),
-        ipv4_dns_entry!("FlashStart", (185, 236, 104, 104), 53),
-        ipv4_dns_entry!("FlashStart", (185, 236, 105, 105), 53),
-        ipv4_dns_entry!("Gcore Public DNS", (95, 85, 95, 85), 53),
-        ipv4_dns_entry!("Gcore Public DNS", (2, 56, 220, 2), 53),
+        ipv4_dns_entry!("Google", (8, 8, 8, 8), 53, [TAG_ANYCAST]),
+        ipv4_dns_entry!("Google", (8, 8, 4, 4), 53, [TAG_ANYCAST]),
+        ipv4_dns_entry!("Cloudflare", (1, 1, 1, 1), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Cloudflare", (1, 0, 0, 1), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Quad9", (9, 9, 9, 9), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Quad9", (149, 112, 112, 112), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Control D", (76, 76, 2, 0), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Control D", (76, 76, 10, 0), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("OpenDNS Home", (208, 67, 222, 222), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv4_dns_entry!("OpenDNS Home", (208, 67, 220, 220), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv4_dns_entry!("CleanBrowsing", (185, 228, 168, 9), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_PRIVACY]),
+        ipv4_dns_entry!("CleanBrowsing", (185, 228, 169, 9), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_PRIVACY]),
+        ipv4_dns_entry!("AdGuard DNS", (94, 140, 14, 14), 53, [TAG_ANYCAST, TAG_AD_BLOCKING, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("AdGuard DNS", (94, 140, 15, 15), 53, [TAG_ANYCAST, TAG_AD_BLOCKING, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Comodo Secure DNS", (8, 26, 56, 26), 53, [TAG_FILTERING]),
+        ipv4_dns_entry!("Comodo Secure DNS", (8, 20, 247, 20), 53, [TAG_FILTERING]),
+        ipv4_dns_entry!("Level3", (209, 244, 0, 3), 53, []),
+        ipv4_dns_entry!("Level3", (209, 244, 0, 4), 53, []),
+        ipv4_dns_entry!("Verisign", (64, 6, 64, 6), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv4_dns_entry!("Verisign", (64, 6, 65, 6), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv4_dns_entry!("DNS.WATCH", (84, 200, 69, 80), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("DNS.WATCH", (84, 200, 70, 40), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Norton ConnectSafe", (199, 85, 126, 10), 53, [TAG_FILTERING]),
+        ipv4_dns_entry!("Norton ConnectSafe", (199, 85, 127, 10), 53, [TAG_FILTERING]),
+        ipv4_dns_entry!("SafeDNS", (195, 46, 39, 39), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv4_dns_entry!("SafeDNS", (195, 46, 39, 40), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv4_dns_entry!("NextDNS", (45, 90, 28, 100), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv4_dns_entry!("NextDNS", (45, 90, 30, 100), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv4_dns_entry!("Dyn", (216, 146, 35, 35), 53, []),
+        ipv4_dns_entry!("Dyn", (216, 146, 36, 36), 53, []),
+        ipv4_dns_entry!("Hurricane Electric", (74, 82, 42, 42), 53, [TAG_ANYCAST]),
+        ipv4_dns_entry!("Surfshark DNS", (162, 252, 172, 57), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Surfshark DNS", (149, 154, 159, 92), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("SafeServe", (198, 54, 117, 10), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("SafeServe", (198, 54, 117, 11), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv4_dns_entry!("Vercara UltraDNS Public", (156, 154, 70, 2), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv4_dns_entry!("Vercara UltraDNS Public", (156, 154, 71, 2), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv4_dns_entry!("FlashStart", (185, 236, 104, 104), 53, [TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv4_dns_entry!("FlashStart", (185, 236, 105, 105), 53, [TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv4_dns_entry!("Gcore Public DNS", (95, 85, 95, 85), 53, [TAG_ANYCAST]),
+        ipv4_dns_entry!("Gcore Public DNS", (2, 56, 220, 2), 53, [TAG_ANYCAST]),
     ];
 }
 
 lazy_static::lazy_static! {
     pub static ref IPV6_DNS_ENTRIES: Vec<DnsEntry> = vec![
-        ipv6_dns_entry!("Google", (0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8888), 53),
-        ipv6_dns_entry!("Google", (0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8844), 53),
-        ipv6_dns_entry!("Cloudflare", (0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111), 53),
-        ipv6_dns_entry!("Cloudflare", (0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1001), 53),
-        ipv6_dns_entry!("Quad9", (0x2620, 0x00fe, 0, 0, 0, 0, 0, 0x00fe), 53),
-        ipv6_dns_entry!("Quad9", (0x2620, 0x00fe, 0, 0, 0, 0, 0, 0x0009), 53),
-        ipv6_dns_entry!("Control D", (0x2606, 0x1a40, 0, 0, 0, 0, 0, 0), 53),
-        ipv6_dns_entry!("Control D", (0x2606, 0x1a40, 0x0001, 0, 0, 0, 0, 0), 53),
-        ipv6_dns_entry!("OpenDNS Home", (0x2620, 0x0119, 0x0035, 0, 0, 0, 0, 0x0035), 53),
-        ipv6_dns_entry!("OpenDNS Home", (0x2620, 0x0119, 0x0053, 0, 0, 0, 0, 0x0053), 53),
-        ipv6_dns_entry!("CleanBrowsing", (0x2a0d, 0x2a00, 0x0001, 0, 0, 0, 0, 0x0002), 53),
-        ipv6_dns_entry!("CleanBrowsing", (0x2a0d, 0x2a00, 0x0002, 0, 0, 0, 0, 0x0002), 53),
-        ipv6_dns_entry!("AdGuard DNS", (0x2a10, 0x50c0, 0, 0, 0, 0, 0x0ad1, 0x00ff), 53),
-        ipv6_dns_entry!("AdGuard DNS", (0x2a10, 0x50c0, 0, 0, 0, 0, 0x0ad2, 0x00ff), 53),
-        ipv6_dns_entry!("Verisign", (0x2620, 0x0074, 0x001b, 0, 0, 0, 0x0001, 0x0001), 53),
-        ipv6_dns_entry!("Verisign", (0x2620, 0x0074, 0x001c, 0, 0, 0, 0x0002, 0x0002), 53),
-        ipv6_dns_entry!("DNS.WATCH", (0x2001, 0x1608, 0x0010, 0x0025, 0, 0, 0x1c04, 0xb12f), 53),
-        ipv6_dns_entry!("DNS.WATCH", (0x2001, 0x1608, 0x0010, 0x0025, 0, 0, 0x9249, 0xd69b), 53),
-        ipv6_dns_entry!("NextDNS", (0x2a07, 0xa8c0, 0, 0, 0, 0, 0x006e, 0x3f39), 53),
-        ipv6_dns_entry!("NextDNS", (0x2a07, 0xa8c1, 0, 0, 0, 0, 0x006e, 0x3f39), 53),
-        ipv6_dns_entry!("Hurricane Electric", (0x2001, 0x0470, 0x0020, 0, 0, 0, 0, 0x0002), 53),
-        ipv6_dns_entry!("Vercara UltraDNS Public", (0x2610, 0x00a1, 0x1018, 0, 0, 0, 0, 0x0002), 53),
-        ipv6_dns_entry!("Vercara UltraDNS Public", (0x2610, 0x00a1, 0x1019, 0, 0, 0, 0, 0x0002), 53),
-        ipv6_dns_entry!("FlashStart", (0x2a12, 0x7bc0, 0x0104, 0x0104, 0, 0, 0, 0), 53),
-        ipv6_dns_entry!("FlashStart", (0x2a12, 0x7bc0, 0x0105, 0x0105, 0, 0, 0, 0), 53),
-        ipv6_dns_entry!("Gcore Public DNS", (0x2a03, 0x90c0, 0x999d, 0, 0, 0, 0, 0x0001), 53),
-        ipv6_dns_entry!("Gcore Public DNS", (0x2a03, 0x90c0, 0x9992, 0, 0, 0, 0, 0x0001), 53),
+        ipv6_dns_entry!("Google", (0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8888), 53, [TAG_ANYCAST]),
+        ipv6_dns_entry!("Google", (0x2001, 0x4860, 0x4860, 0, 0, 0, 0, 0x8844), 53, [TAG_ANYCAST]),
+        ipv6_dns_entry!("Cloudflare", (0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Cloudflare", (0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1001), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Quad9", (0x2620, 0x00fe, 0, 0, 0, 0, 0, 0x00fe), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Quad9", (0x2620, 0x00fe, 0, 0, 0, 0, 0, 0x0009), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Control D", (0x2606, 0x1a40, 0, 0, 0, 0, 0, 0), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Control D", (0x2606, 0x1a40, 0x0001, 0, 0, 0, 0, 0), 53, [TAG_ANYCAST, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("OpenDNS Home", (0x2620, 0x0119, 0x0035, 0, 0, 0, 0, 0x0035), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv6_dns_entry!("OpenDNS Home", (0x2620, 0x0119, 0x0053, 0, 0, 0, 0, 0x0053), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv6_dns_entry!("CleanBrowsing", (0x2a0d, 0x2a00, 0x0001, 0, 0, 0, 0, 0x0002), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_PRIVACY]),
+        ipv6_dns_entry!("CleanBrowsing", (0x2a0d, 0x2a00, 0x0002, 0, 0, 0, 0, 0x0002), 53, [TAG_ANYCAST, TAG_FILTERING, TAG_PRIVACY]),
+        ipv6_dns_entry!("AdGuard DNS", (0x2a10, 0x50c0, 0, 0, 0, 0, 0x0ad1, 0x00ff), 53, [TAG_ANYCAST, TAG_AD_BLOCKING, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("AdGuard DNS", (0x2a10, 0x50c0, 0, 0, 0, 0, 0x0ad2, 0x00ff), 53, [TAG_ANYCAST, TAG_AD_BLOCKING, TAG_FILTERING, TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("Verisign", (0x2620, 0x0074, 0x001b, 0, 0, 0, 0x0001, 0x0001), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv6_dns_entry!("Verisign", (0x2620, 0x0074, 0x001c, 0, 0, 0, 0x0002, 0x0002), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv6_dns_entry!("DNS.WATCH", (0x2001, 0x1608, 0x0010, 0x0025, 0, 0, 0x1c04, 0xb12f), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("DNS.WATCH", (0x2001, 0x1608, 0x0010, 0x0025, 0, 0, 0x9249, 0xd69b), 53, [TAG_NO_LOGGING, TAG_PRIVACY]),
+        ipv6_dns_entry!("NextDNS", (0x2a07, 0xa8c0, 0, 0, 0, 0, 0x006e, 0x3f39), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv6_dns_entry!("NextDNS", (0x2a07, 0xa8c1, 0, 0, 0, 0, 0x006e, 0x3f39), 53, [TAG_ANYCAST, TAG_PRIVACY]),
+        ipv6_dns_entry!("Hurricane Electric", (0x2001, 0x0470, 0x0020, 0, 0, 0, 0, 0x0002), 53, [TAG_ANYCAST]),
+        ipv6_dns_entry!("Vercara UltraDNS Public", (0x2610, 0x00a1, 0x1018, 0, 0, 0, 0, 0x0002), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv6_dns_entry!("Vercara UltraDNS Public", (0x2610, 0x00a1, 0x1019, 0, 0, 0, 0, 0x0002), 53, [TAG_ANYCAST, TAG_FILTERING]),
+        ipv6_dns_entry!("FlashStart", (0x2a12, 0x7bc0, 0x0104, 0x0104, 0, 0, 0, 0), 53, [TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv6_dns_entry!("FlashStart", (0x2a12, 0x7bc0, 0x0105, 0x0105, 0, 0, 0, 0), 53, [TAG_FILTERING, TAG_FAMILY_SAFE]),
+        ipv6_dns_entry!("Gcore Public DNS", (0x2a03, 0x90c0, 0x999d, 0, 0, 0, 0, 0x0001), 53, [TAG_ANYCAST]),
+        ipv6_dns_entry!("Gcore Public DNS", (0x2a03, 0x90c0, 0x9992, 0, 0, 0, 0, 0x0001), 53, [TAG_ANYCAST]),
     ];
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dns_entry_filter() {
+        let names_of = |filter: &DnsEntryFilter| {
+            let mut names = IPV4_DNS_ENTRIES
+                .iter()
+                .filter(|entry| filter.matches(entry))
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>();
+            names.dedup();
+            names
+        };
+
+        assert_eq!(names_of(&DnsEntryFilter::default()).len(), 21);
+        assert_eq!(
+            names_of(&DnsEntryFilter {
+                include_tags: vec![String::from("AD-BLOCKING")],
+                ..Default::default()
+            }),
+            ["AdGuard DNS"]
+        );
+        assert_eq!(
+            names_of(&DnsEntryFilter {
+                include_tags: vec![String::from(TAG_NO_LOGGING)],
+                exclude_tags: vec![String::from(TAG_FILTERING)],
+                ..Default::default()
+            }),
+            [
+                "Cloudflare",
+                "Control D",
+                "DNS.WATCH",
+                "Surfshark DNS",
+                "SafeServe"
+            ]
+        );
+        assert_eq!(
+            names_of(&DnsEntryFilter {
+                only: vec![String::from("google"), String::from("Quad9")],
+                ..Default::default()
+            }),
+            ["Google", "Quad9"]
+        );
+
+        let filter = DnsEntryFilter {
+            only: vec![String::from("Google")],
+            exclude: vec![String::from("8.8.4.4")],
+            ..Default::default()
+        };
+        let entries = IPV4_DNS_ENTRIES
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].socket_addr, "8.8.8.8:53".parse().unwrap());
+    }
+
+    #[test]
+    fn test_dns_entry_filter_detected_servers() {
+        let gateway = DnsEntry::new(
+            String::from("Router (Gateway) DNS"),
+            "192.168.1.1:53".parse().unwrap(),
+        );
+        let system = DnsEntry::new(
+            String::from("System DNS (resolv.conf)"),
+            "192.168.1.53:53".parse().unwrap(),
+        );
+
+        // Tag filters do not drop the untagged detected servers.
+        let filter = DnsEntryFilter {
+            include_tags: vec![String::from(TAG_PRIVACY)],
+            exclude: vec![String::from("192.168.1.1")],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&system));
+        assert!(filter.matches_name_or_ip(&system));
+        assert!(!filter.matches_name_or_ip(&gateway));
+
+        let filter = DnsEntryFilter {
+            only: vec![String::from("Cloudflare")],
+            ..Default::default()
+        };
+        assert!(!filter.matches_name_or_ip(&gateway));
+        assert!(!filter.matches_name_or_ip(&system));
+    }
+
+    #[test]
+    fn test_dns_entry_is_applicable() {
+        let entry =
+            |socket_addr: &str| DnsEntry::new(String::from("Test"), socket_addr.parse().unwrap());
+
+        assert!(entry("1.1.1.1:53").is_applicable(Protocol::Udp));
+        assert!(entry("1.1.1.1:53").is_applicable(Protocol::Tcp));
+        assert!(!entry("1.1.1.1:53").is_applicable(Protocol::Tls));
+        assert!(!entry("1.1.1.1:5353").is_applicable(Protocol::Udp));
+        assert!(!entry("127.0.0.53:53").is_applicable(Protocol::Udp));
+        assert!(!DnsEntry {
+            protocol: Some(Protocol::Tls),
+            ..entry("1.1.1.1:53")
+        }
+        .is_applicable(Protocol::Udp));
+        assert!(DnsEntry {
+            protocol: Some(Protocol::Udp),
+            ..entry("1.1.1.1:53")
+        }
+        .is_applicable(Protocol::Https));
+    }
+}
@@ -0,0 +1,409 @@
+use crate::args::Protocol;
+use crate::custom::DohUrl;
+use crate::custom::Host;
+use crate::custom::ServerSpec;
+use crate::servers::TAG_FILTERING;
+use crate::servers::TAG_NO_LOGGING;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use std::fmt;
+use std::net;
+use std::str::FromStr;
+
+pub const STAMP_PREFIX: &str = "sdns://";
+
+const PROTOCOL_PLAIN: u8 = 0x00;
+const PROTOCOL_DNSCRYPT: u8 = 0x01;
+const PROTOCOL_DOH: u8 = 0x02;
+const PROTOCOL_DOT: u8 = 0x03;
+const PROTOCOL_DOQ: u8 = 0x04;
+const PROTOCOL_ODOH: u8 = 0x05;
+const PROTOCOL_DNSCRYPT_RELAY: u8 = 0x81;
+const PROTOCOL_ODOH_RELAY: u8 = 0x85;
+
+const PROPERTY_NO_LOGS: u64 = 1 << 1;
+const PROPERTY_NO_FILTER: u64 = 1 << 2;
+
+#[derive(Debug, derive_more::Error)]
+pub enum StampError {
+    InvalidPrefix,
+    InvalidBase64(base64::DecodeError),
+    Truncated,
+    Invalid(#[error(not(source))] String),
+    UnsupportedProtocol(#[error(not(source))] &'static str),
+    UnsupportedPath(#[error(not(source))] String),
+}
+
+impl StampError {
+    /// The stamp is valid, but describes a server which cannot be benchmarked.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            StampError::UnsupportedProtocol(_) | StampError::UnsupportedPath(_)
+        )
+    }
+}
+
+impl fmt::Display for StampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StampError::InvalidPrefix => write!(f, "DNS stamp must start with {STAMP_PREFIX}"),
+            StampError::InvalidBase64(e) => write!(f, "Invalid DNS stamp encoding: {e}"),
+            StampError::Truncated => write!(f, "DNS stamp is truncated"),
+            StampError::Invalid(message) => write!(f, "Invalid DNS stamp: {message}"),
+            StampError::UnsupportedProtocol(protocol) => {
+                write!(f, "{protocol} servers are not supported")
+            }
+            StampError::UnsupportedPath(path) => {
+                write!(f, "DoH path \"{path}\" is not supported")
+            }
+        }
+    }
+}
+
+/// Reads the length-prefixed fields of a decoded stamp.
+struct StampReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StampReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StampError> {
+        if self.bytes.len() < len {
+            return Err(StampError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, StampError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn properties(&mut self) -> Result<u64, StampError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes taken")))
+    }
+
+    fn string(&mut self) -> Result<String, StampError> {
+        let len = self.byte()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| StampError::Invalid(String::from("field is not UTF-8")))
+    }
+
+    /// Skip a set of values where the high bit of each length marks that more follow.
+    fn skip_set(&mut self) -> Result<(), StampError> {
+        loop {
+            let len = self.byte()?;
+            self.take((len & 0x7f) as usize)?;
+            if len & 0x80 == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// A server described by a DNS stamp (`sdns://`), as distributed in public resolver lists.
+///
+/// Only plain DNS, DNS over TLS and DNS over HTTPS stamps are supported.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DnsStamp {
+    pub protocol: Protocol,
+    /// Empty for DoH and DoT servers which are found by host name.
+    pub address: Option<net::IpAddr>,
+    pub port: Option<u16>,
+    pub hostname: Option<String>,
+    /// The DoH path.
+    pub path: Option<String>,
+    pub no_logs: bool,
+    pub no_filter: bool,
+}
+
+impl DnsStamp {
+    /// Name of the server when the list does not give one.
+    pub fn default_name(&self) -> String {
+        match (&self.hostname, self.address) {
+            (Some(hostname), _) => hostname.clone(),
+            (None, Some(address)) => address.to_string(),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Describe the server in the structured custom servers format.
+    ///
+    /// Plain DNS servers are benchmarked over UDP when an encrypted protocol is configured.
+    pub fn into_server_spec(
+        self,
+        name: String,
+        default_protocol: Protocol,
+    ) -> Result<ServerSpec, StampError> {
+        let mut tags = Vec::new();
+        if !self.no_filter {
+            tags.push(String::from(TAG_FILTERING));
+        }
+        if self.no_logs {
+            tags.push(String::from(TAG_NO_LOGGING));
+        }
+        let (protocol, doh_url) = match self.protocol {
+            Protocol::Https => {
+                let hostname = self.hostname.as_deref().ok_or_else(|| {
+                    StampError::Invalid(String::from("DoH stamp has no host name"))
+                })?;
+                let port = self.port.map(|port| format!(":{port}")).unwrap_or_default();
+                let path = self.path.unwrap_or_default();
+                let doh_url = DohUrl::try_from(format!("https://{hostname}{port}{path}"))
+                    .map_err(|_| StampError::UnsupportedPath(path))?;
+                (Some(Protocol::Https), Some(doh_url))
+            }
+            Protocol::Tls => (Some(Protocol::Tls), None),
+            Protocol::Udp | Protocol::Tcp => match default_protocol {
+                Protocol::Udp | Protocol::Tcp => (None, None),
+                Protocol::Tls | Protocol::Https => (Some(Protocol::Udp), None),
+            },
+        };
+        let addresses = match (self.address, &self.hostname) {
+            (Some(address), _) => vec![Host::Ip(address)],
+            (None, Some(hostname)) if doh_url.is_none() => {
+                vec![hostname.parse().map_err(StampError::Invalid)?]
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(ServerSpec {
+            name,
+            addresses,
+            port: self.port,
+            protocol,
+            tls_name: self.hostname.filter(|_| protocol == Some(Protocol::Tls)),
+            doh_url,
+            timeout: None,
+            requests: None,
+            qps: None,
+            interval: None,
+            tags,
+            bind: None,
+            enabled: true,
+        })
+    }
+}
+
+/// Parse `ip`, `ip:port`, `[ipv6]` or `[ipv6]:port`, an empty address is allowed.
+fn parse_stamp_address(address: &str) -> Result<(Option<net::IpAddr>, Option<u16>), StampError> {
+    let invalid = || StampError::Invalid(format!("invalid address \"{address}\""));
+    if address.is_empty() {
+        return Ok((None, None));
+    }
+    if let Ok(address) = address.parse::<net::IpAddr>() {
+        return Ok((Some(address), None));
+    }
+    if let Ok(socket_addr) = address.parse::<net::SocketAddr>() {
+        return Ok((Some(socket_addr.ip()), Some(socket_addr.port())));
+    }
+    let address = address
+        .strip_prefix('[')
+        .and_then(|address| address.strip_suffix(']'))
+        .ok_or_else(invalid)?;
+    Ok((Some(address.parse().map_err(|_| invalid())?), None))
+}
+
+/// Split the port off `host:port`.
+fn parse_stamp_hostname(hostname: String) -> Result<(String, Option<u16>), StampError> {
+    match hostname.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse()
+                .map_err(|_| StampError::Invalid(format!("invalid host name \"{hostname}\"")))?;
+            Ok((host.to_string(), Some(port)))
+        }
+        None => Ok((hostname, None)),
+    }
+}
+
+impl FromStr for DnsStamp {
+    type Err = StampError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let encoded = s
+            .strip_prefix(STAMP_PREFIX)
+            .ok_or(StampError::InvalidPrefix)?;
+        let bytes = URL_SAFE_NO_PAD
+            .decode(encoded.trim_end_matches('='))
+            .map_err(StampError::InvalidBase64)?;
+        let mut reader = StampReader { bytes: &bytes };
+
+        let protocol = match reader.byte()? {
+            PROTOCOL_PLAIN => Protocol::Udp,
+            PROTOCOL_DOH => Protocol::Https,
+            PROTOCOL_DOT => Protocol::Tls,
+            PROTOCOL_DNSCRYPT => return Err(StampError::UnsupportedProtocol("DNSCrypt")),
+            PROTOCOL_DOQ => return Err(StampError::UnsupportedProtocol("DNS over QUIC")),
+            PROTOCOL_ODOH => return Err(StampError::UnsupportedProtocol("Oblivious DoH")),
+            PROTOCOL_DNSCRYPT_RELAY | PROTOCOL_ODOH_RELAY => {
+                return Err(StampError::UnsupportedProtocol("Relay"))
+            }
+            protocol => {
+                return Err(StampError::Invalid(format!(
+                    "unknown protocol {protocol:#04x}"
+                )))
+            }
+        };
+        let properties = reader.properties()?;
+        let (address, mut port) = parse_stamp_address(&reader.string()?)?;
+
+        let (hostname, path) = match protocol {
+            Protocol::Https | Protocol::Tls => {
+                reader.skip_set()?;
+                let (hostname, hostname_port) = parse_stamp_hostname(reader.string()?)?;
+                port = port.or(hostname_port);
+                let path = match protocol {
+                    Protocol::Https => Some(reader.string()?),
+                    _ => None,
+                };
+                (Some(hostname).filter(|hostname| !hostname.is_empty()), path)
+            }
+            Protocol::Udp | Protocol::Tcp => (None, None),
+        };
+        if address.is_none() && hostname.is_none() {
+            return Err(StampError::Invalid(String::from(
+                "neither address nor host name given",
+            )));
+        }
+
+        Ok(DnsStamp {
+            protocol,
+            address,
+            port,
+            hostname,
+            path,
+            no_logs: properties & PROPERTY_NO_LOGS != 0,
+            no_filter: properties & PROPERTY_NO_FILTER != 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_stamp() {
+        let stamp = "sdns://AAcAAAAAAAAACDkuOS45LjEw"
+            .parse::<DnsStamp>()
+            .unwrap();
+        assert_eq!(
+            stamp,
+            DnsStamp {
+                protocol: Protocol::Udp,
+                address: Some("9.9.9.10".parse().unwrap()),
+                port: None,
+                hostname: None,
+                path: None,
+                no_logs: true,
+                no_filter: true,
+            }
+        );
+
+        let stamp = "sdns://AAEAAAAAAAAAElsyNjIwOmZlOjoxMF06NTM1Mw"
+            .parse::<DnsStamp>()
+            .unwrap();
+        assert_eq!(stamp.address, Some("2620:fe::10".parse().unwrap()));
+        assert_eq!(stamp.port, Some(5353));
+        assert!(!stamp.no_logs);
+    }
+
+    #[test]
+    fn test_parse_doh_stamp() {
+        let stamp = "sdns://AgcAAAAAAAAABzEuMC4wLjEAEmRucy5jbG91ZGZsYXJlLmNvbQovZG5zLXF1ZXJ5"
+            .parse::<DnsStamp>()
+            .unwrap();
+        assert_eq!(
+            stamp,
+            DnsStamp {
+                protocol: Protocol::Https,
+                address: Some("1.0.0.1".parse().unwrap()),
+                port: None,
+                hostname: Some(String::from("dns.cloudflare.com")),
+                path: Some(String::from("/dns-query")),
+                no_logs: true,
+                no_filter: true,
+            }
+        );
+
+        let stamp = "sdns://AgIAAAAAAAAAEVsyNjA2OjQ3MDA6OjExMTFdABdjbG91ZGZsYXJlLWRucy5jb206ODQ0MwovZG5zLXF1ZXJ5"
+            .parse::<DnsStamp>()
+            .unwrap();
+        assert_eq!(stamp.address, Some("2606:4700::1111".parse().unwrap()));
+        assert_eq!(stamp.hostname.as_deref(), Some("cloudflare-dns.com"));
+        assert_eq!(stamp.port, Some(8443));
+    }
+
+    #[test]
+    fn test_parse_invalid_stamp() {
+        assert!(matches!(
+            "https://dns.google".parse::<DnsStamp>(),
+            Err(StampError::InvalidPrefix)
+        ));
+        assert!(matches!(
+            "sdns://!!!".parse::<DnsStamp>(),
+            Err(StampError::InvalidBase64(_))
+        ));
+        assert!(matches!(
+            "sdns://AwEAAAAAAAAABzkuOS45Ljk".parse::<DnsStamp>(),
+            Err(StampError::Truncated)
+        ));
+
+        let Err(e) = "sdns://AQEAAAAAAAAADjIwOC42Ny4yMjAuMjIwIBERERERERERERERERERERERERERERERERERERERERERGzIuZG5zY3J5cHQtY2VydC5vcGVuZG5zLmNvbQ"
+            .parse::<DnsStamp>()
+        else {
+            panic!("expected an unsupported protocol");
+        };
+        assert!(e.is_unsupported());
+        assert_eq!(e.to_string(), "DNSCrypt servers are not supported");
+    }
+
+    #[test]
+    fn test_into_server_spec() {
+        let stamp = "sdns://AwMAAAAAAAAABzkuOS45LjkADWRucy5xdWFkOS5uZXQ"
+            .parse::<DnsStamp>()
+            .unwrap();
+        let spec = stamp
+            .clone()
+            .into_server_spec(stamp.default_name(), Protocol::Udp)
+            .unwrap();
+        assert_eq!(spec.name, "dns.quad9.net");
+        assert_eq!(spec.addresses, [Host::Ip("9.9.9.9".parse().unwrap())]);
+        assert_eq!(spec.protocol, Some(Protocol::Tls));
+        assert_eq!(spec.tls_name.as_deref(), Some("dns.quad9.net"));
+        assert_eq!(spec.tags, [TAG_FILTERING, TAG_NO_LOGGING]);
+
+        let stamp = "sdns://AgcAAAAAAAAAACABAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQpkbnMuZ29vZ2xlCi9kbnMtcXVlcnk"
+            .parse::<DnsStamp>()
+            .unwrap();
+        let spec = stamp
+            .into_server_spec(String::from("Google"), Protocol::Udp)
+            .unwrap();
+        assert!(spec.addresses.is_empty());
+        assert_eq!(
+            spec.doh_url.map(String::from).as_deref(),
+            Some("https://dns.google/dns-query")
+        );
+
+        let stamp = "sdns://AAcAAAAAAAAACDkuOS45LjEw"
+            .parse::<DnsStamp>()
+            .unwrap();
+        let spec = stamp
+            .into_server_spec(String::from("Quad9"), Protocol::Tls)
+            .unwrap();
+        assert_eq!(spec.protocol, Some(Protocol::Udp));
+        assert_eq!(spec.tags, [TAG_NO_LOGGING]);
+
+        let stamp = "sdns://AgAAAAAAAAAADDk0LjE0MC4xNC4xNAATZG5zLmFkZ3VhcmQtZG5zLmNvbQgvcmVzb2x2ZQ"
+            .parse::<DnsStamp>()
+            .unwrap();
+        let Err(e) = stamp.into_server_spec(String::from("AdGuard"), Protocol::Udp) else {
+            panic!("expected an unsupported path");
+        };
+        assert!(e.is_unsupported());
+    }
+}